@@ -67,7 +67,7 @@ Instantiate() {
     
     #read from FILE_CODE_ID
     CODE_ID=$(cat $FILE_CODE_ID)
-    junod tx wasm instantiate $CODE_ID '{"owner":"'$ADDR_FORTIS'", "fot_token_address":"'$FOT_ADDRESS'","bfot_token_address":"'$BFOT_ADDRESS'", "gfot_token_address":"'$GFOT_ADDRESS'", "daily_fot_amount":"100000000000000", "apy_prefix":"109500000", "reward_interval":86400, "lock_days":14}' --label "SFOT-GFOT LP Staking" $WALLET $TXFLAG -y
+    junod tx wasm instantiate $CODE_ID '{"owner":"'$ADDR_FORTIS'", "fot_token_address":"'$FOT_ADDRESS'","bfot_token_address":"'$BFOT_ADDRESS'", "stake_token":{"token":{"contract_addr":"'$GFOT_ADDRESS'"}}, "daily_fot_amount":"100000000000000", "apy_prefix":"109500000", "reward_interval":86400, "lock_days":14}' --label "SFOT-GFOT LP Staking" $WALLET $TXFLAG -y
 }
 
 #Get Instantiated Contract Address
@@ -119,7 +119,7 @@ WithdrawFot() {
 
 WithdrawGFot() {
     CONTRACT_GFOTSTAKING=$(cat $FILE_CONTRACT_ADDR)
-    junod tx wasm execute $CONTRACT_GFOTSTAKING '{"withdraw_g_fot":{}}' $WALLET $TXFLAG -y
+    junod tx wasm execute $CONTRACT_GFOTSTAKING '{"withdraw_stake":{}}' $WALLET $TXFLAG -y
 }
 
 ClaimReward() {
@@ -101,7 +101,8 @@ async function main() {
                     newContractAddr,
                     { 
                         add_stakers: {
-                            stakers: stakerslist
+                            stakers: stakerslist,
+                            batch_id: queryMsg.start_after
                         }
                     },
                     'auto',
@@ -111,6 +112,9 @@ async function main() {
                 break;
 
             } catch (error) {
+                // a retried batch that already landed is rejected by the contract
+                if (String(error).includes('already imported'))
+                    break;
                 continue;
             }
         }
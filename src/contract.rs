@@ -1,22 +1,36 @@
-use std::collections::btree_set::Difference;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, to_binary, from_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
-    WasmMsg, WasmQuery, QueryRequest, CosmosMsg, Order, Addr, Decimal, Storage, Api
+    attr, coins, to_json_binary, from_json, BankMsg, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdResult, StdError, Uint128,
+    WasmMsg, WasmQuery, QueryRequest, Order, Addr, Storage, SubMsg, Decimal, Decimal256, Uint256, Api
 };
 use cw2::{get_contract_version, set_contract_version};
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Cw20QueryMsg, Cw20CoinVerified};
-use cw20::{TokenInfoResponse, Balance};
-use cw_utils::{maybe_addr};
+use cw20::{Balance, Cw20CoinVerified, Cw20ExecuteMsg, Cw20ReceiveMsg, Cw20QueryMsg};
+use cw20::TokenInfoResponse;
+use cw_utils::{maybe_addr, must_pay, Expiration, Scheduled};
+use sha2::Digest;
 use cw_storage_plus::Bound;
 use crate::error::ContractError;
 use crate::msg::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg, StakerListResponse, StakerInfo, CountInfo, StakerResponse, SfotRewardInfo
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, StakerListResponse, StakerInfo, StakerResponse, SfotRewardInfo, OperatorResponse,
+    NftPositionResponse, NftPositionListResponse, PositionResponse, PositionsResponse, UnbondingEntry,
+    StakedBalanceAtHeightResponse, TotalStakedAtHeightResponse,
+    VotingPowerAtHeightResponse, TotalPowerAtHeightResponse, InfoResponse,
+    Member, MemberDiff, MemberResponse, MemberListResponse, TotalWeightResponse, HooksResponse,
+    MemberChangedHookMsg, MemberChangedExecuteMsg, ApyResponse, OrderBy, StatsResponse, SolvencyResponse, PurgeStakersResponse,
+    SfotStageResponse, LatestSfotStageResponse, SfotClaimedResponse, SfotTotalsResponse, ReceiveMsg, AdminResponse, PoolConfigResponse, PoolListResponse,
+    DepositResponse, DepositListResponse, EmissionScheduleResponse, VestingResponse, BoostResponse, Cw721ReceiveMsg, ImportMode, AssetInfo, Asset, PairQueryMsg, PoolResponse, SimulationResponse, Cw721ExecuteMsg, Cw721MintMsg, Cw721QueryMsg, Cw721OwnerOfResponse
 };
 use crate::state::{
-    Config, CONFIG, STAKERS, UNSTAKING
+    Config, CONFIG, GFOT_DECIMALS, ADMIN, POOLS, POOL_COUNT, stakers, LEGACY_STAKERS, LEGACY_UNSTAKING, LegacyMigration, LEGACY_MIGRATION,
+    amount_index_key, StakerData, UNSTAKING, OPERATORS, Position, positions, POSITION_COUNT, POSITION_STAKES,
+    STAKED_BALANCES, STAKED_TOTAL, HOOKS, PriceSource, Stats, STATS, RewardState, REWARD_STATE, REWARD_INDEX, IOUS, IMPORT_BATCHES,
+    SfotStage, SFOT_STAGES, LATEST_SFOT_STAGE, SFOT_CLAIMED, SfotTotals, SFOT_TOTALS, Term, Deposit, DEPOSITS, DEPOSIT_COUNT,
+    EmissionSchedule, EmissionSegment, Vesting, VestingEntry, VESTING, Loyalty, LoyaltyCurve, StakeAge, STAKE_AGES,
+    Boost, BoostLock, LockedNft, BOOST_LOCKS
 };
 
 // Version info, for migration info
@@ -24,38 +38,129 @@ const CONTRACT_NAME: &str = "gfotstaking";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 // const DAILY_FOT_AMOUNT:u128 = 100_000_000_000_000u128;
-const MULTIPLE:u128 = 10_000_000_000u128;
+const SECONDS_PER_YEAR:u64 = 365 * 86400;
+const BPS_DENOMINATOR:u64 = 10_000;
+// periods a decay emission may take to reach zero, bounding the pieces of an update
+const MAX_DECAY_PERIODS:u64 = 1_000;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    let config = pool_config(deps.api, &info, msg)?;
+    ADMIN.save(deps.storage, &config.owner)?;
+    POOL_COUNT.save(deps.storage, &1)?;
+    save_new_pool(deps.storage, &env, 0, &config)?;
+
+    Ok(Response::default())
+}
+
+/// Pool config from an InstantiateMsg, owned by the sender if no owner is set
+fn pool_config(api: &dyn Api, info: &MessageInfo, msg: InstantiateMsg) -> StdResult<Config> {
     let owner = msg
         .owner
-        .map_or(Ok(info.sender), |o| deps.api.addr_validate(&o))?;
+        .map_or(Ok(info.sender.clone()), |o| api.addr_validate(&o))?;
+    let dao = maybe_addr(api, msg.dao)?;
+    let price_source = validate_price_source(api, msg.price_source.unwrap_or_default())?;
+    let reward_interval = msg.reward_interval;
 
-    let config = Config {
+    Ok(Config {
         owner: Some(owner),
         fot_token_address: msg.fot_token_address,
         bfot_token_address:msg.bfot_token_address,
-        gfot_token_address: msg.gfot_token_address,
+        stake_token: validate_stake_token(api, msg.stake_token)?,
+        reward_denom: msg.reward_denom,
         fot_amount: Uint128::zero(),
-        gfot_amount: Uint128::zero(),
+        staked_amount: Uint128::zero(),
         daily_fot_amount: msg.daily_fot_amount,
         apy_prefix: msg.apy_prefix,
         delta_time: msg.delta_time,
-        reward_interval: msg.reward_interval,
+        reward_interval,
         lock_days: msg.lock_days,
-        enabled: true
-    };
-    CONFIG.save(deps.storage, &config)?;
+        enabled: true,
+        transfer_enabled: msg.transfer_enabled,
+        position_nft: None,
+        dao,
+        price_source,
+        stake_decimals: validate_stake_decimals(msg.stake_decimals.unwrap_or(GFOT_DECIMALS))?,
+        underfunded_payouts: false,
+        import_finalized: false,
+        term: msg.term.map(validate_term).transpose()?,
+        emission: msg.emission.map(|schedule| validate_emission_schedule(schedule, reward_interval)).transpose()?,
+        reserved_amount: Uint128::zero(),
+        deposited_amount: Uint128::zero(),
+        vesting: msg.vesting.map(validate_vesting).transpose()?,
+        vesting_amount: Uint128::zero(),
+        loyalty: msg.loyalty.map(validate_loyalty).transpose()?,
+        boost: msg.boost.map(|boost| validate_boost(api, boost)).transpose()?,
+        fee_bps: validate_fee_bps(msg.fee_bps)?,
+        treasury: maybe_addr(api, msg.treasury)?,
+        fee_amount: Uint128::zero()
+    })
+}
 
-    Ok(Response::default())
+fn save_new_pool(storage: &mut dyn Storage, env: &Env, pool_id: u64, config: &Config) -> StdResult<()> {
+    POOLS.save(storage, pool_id, config)?;
+    REWARD_STATE.save(storage, pool_id, &RewardState {
+        last_update: env.block.time.seconds(),
+        ..RewardState::default()
+    })?;
+    STAKED_TOTAL.save(storage, pool_id, &Uint128::zero(), env.block.height)
+}
+
+pub fn execute_create_pool(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: InstantiateMsg
+) -> Result<Response, ContractError> {
+    // authorize admin
+    check_admin(&deps, &info)?;
+
+    let config = pool_config(deps.api, &info, pool)?;
+    let pool_id = POOL_COUNT.load(deps.storage)?;
+    POOL_COUNT.save(deps.storage, &(pool_id + 1))?;
+    save_new_pool(deps.storage, &env, pool_id, &config)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "create_pool"),
+            attr("pool_id", pool_id.to_string()),
+        ]))
+}
+
+pub fn execute_update_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_admin: Option<String>
+) -> Result<Response, ContractError> {
+    // authorize admin
+    check_admin(&deps, &info)?;
+
+    let new_admin = maybe_addr(deps.api, new_admin)?;
+    ADMIN.save(deps.storage, &new_admin)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "update_admin"),
+            attr("admin", new_admin.map(|a| a.to_string()).unwrap_or_default()),
+        ]))
+}
+
+pub fn check_admin(
+    deps: &DepsMut,
+    info: &MessageInfo
+) -> Result<Response, ContractError> {
+    let admin = ADMIN.may_load(deps.storage)?.flatten().ok_or(ContractError::Unauthorized {})?;
+    if info.sender != admin {
+        return Err(ContractError::Unauthorized {})
+    }
+    Ok(Response::new().add_attribute("action", "check_admin"))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -66,603 +171,4929 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateConfig { new_owner } => execute_update_config(deps, info, new_owner),
-        ExecuteMsg::UpdateConstants { daily_fot_amount, apy_prefix , reward_interval, delta_time, lock_days, enabled} => execute_update_constants(deps, info, daily_fot_amount, apy_prefix, reward_interval, delta_time, lock_days, enabled),
+        ExecuteMsg::CreatePool { pool } => execute_create_pool(deps, env, info, *pool),
+        ExecuteMsg::UpdateAdmin { new_admin } => execute_update_admin(deps, info, new_admin),
+        ExecuteMsg::UpdateConfig { pool_id, new_owner } => execute_update_config(deps, info, pool_id, new_owner),
+        ExecuteMsg::UpdateConstants { pool_id, daily_fot_amount, apy_prefix , reward_interval, delta_time, lock_days, enabled } => execute_update_constants(deps, env, info, pool_id, daily_fot_amount, apy_prefix, reward_interval, delta_time, lock_days, enabled),
         ExecuteMsg::Receive(msg) => try_receive(deps, env, info, msg),
-        ExecuteMsg::WithdrawFot {} => try_withdraw_fot(deps, env, info),
-        ExecuteMsg::WithdrawGFot {} => try_withdraw_gfot(deps, env, info),
-        ExecuteMsg::ClaimReward {} => try_claim_reward(deps, env, info),
-        ExecuteMsg::CreateUnstake {unstake_amount} => try_create_unstake(deps, env, info, unstake_amount),
-        ExecuteMsg::FetchUnstake {index} => try_fetch_unstake(deps, env, info, index),
-        ExecuteMsg::AddStakers { stakers } => execute_add_stakers(deps, info, stakers),
-        ExecuteMsg::RemoveStaker { address } => execute_remove_staker(deps, info, address),
-        ExecuteMsg::RemoveAllStakers { start_after, limit } => execute_remove_all_stakers(deps, info, start_after, limit),
-        ExecuteMsg::AddSfotRewards { rewards } => execute_add_sfot_rewards(deps, info, rewards),
+        ExecuteMsg::Stake { pool_id } => try_stake(deps, env, info, pool_id),
+        ExecuteMsg::FundRewards { pool_id } => try_fund_rewards(deps, env, info, pool_id),
+        ExecuteMsg::WithdrawFot { pool_id } => try_withdraw_fot(deps, env, info, pool_id),
+        ExecuteMsg::WithdrawStake { pool_id } => try_withdraw_stake(deps, env, info, pool_id),
+        ExecuteMsg::ClaimReward { pool_id, staker, recipient } => try_claim_reward(deps, env, info, pool_id, staker, recipient),
+        ExecuteMsg::CreateUnstake { pool_id, unstake_amount, staker } => try_create_unstake(deps, env, info, pool_id, unstake_amount, staker),
+        ExecuteMsg::FetchUnstake { pool_id, index, staker, recipient } => try_fetch_unstake(deps, env, info, pool_id, index, staker, recipient),
+        ExecuteMsg::ApproveOperator { operator, expires } => execute_approve_operator(deps, env, info, operator, expires),
+        ExecuteMsg::RevokeOperator { operator } => execute_revoke_operator(deps, info, operator),
+        ExecuteMsg::TransferStake { pool_id, recipient, amount, include_reward } => try_transfer_stake(deps, env, info, pool_id, recipient, amount, include_reward.unwrap_or(false)),
+        ExecuteMsg::SetTransferEnabled { pool_id, enabled } => execute_set_transfer_enabled(deps, info, pool_id, enabled),
+        ExecuteMsg::SetUnderfundedPayouts { pool_id, enabled } => execute_set_underfunded_payouts(deps, info, pool_id, enabled),
+        ExecuteMsg::SetPositionNft { pool_id, address } => execute_set_position_nft(deps, info, pool_id, address),
+        ExecuteMsg::SetDao { pool_id, dao } => execute_set_dao(deps, info, pool_id, dao),
+        ExecuteMsg::SetPriceSource { pool_id, price_source } => execute_set_price_source(deps, info, pool_id, price_source),
+        ExecuteMsg::AddHook { pool_id, addr } => execute_add_hook(deps, info, pool_id, addr),
+        ExecuteMsg::RemoveHook { pool_id, addr } => execute_remove_hook(deps, info, pool_id, addr),
+        ExecuteMsg::ClaimPositionReward { pool_id, token_id, recipient } => try_claim_position_reward(deps, env, info, pool_id, token_id, recipient),
+        ExecuteMsg::AddStakers { pool_id, stakers, mode, batch_id } => execute_add_stakers(deps, env, info, pool_id, stakers, mode.unwrap_or_default(), batch_id),
+        ExecuteMsg::FinalizeImport { pool_id } => execute_finalize_import(deps, info, pool_id),
+        ExecuteMsg::MigrateStakers { limit } => execute_migrate_stakers(deps, env, info, limit),
+        ExecuteMsg::RemoveStaker { pool_id, address } => execute_force_exit(deps, env, info, pool_id, address.into()),
+        ExecuteMsg::RemoveAllStakers { pool_id, start_after, limit } => execute_purge_stakers(deps, env, info, pool_id, start_after, limit),
+        ExecuteMsg::ForceExit { pool_id, address } => execute_force_exit(deps, env, info, pool_id, address),
+        ExecuteMsg::PurgeStakers { pool_id, start_after, limit } => execute_purge_stakers(deps, env, info, pool_id, start_after, limit),
+        ExecuteMsg::AddSfotRewards { pool_id, rewards } => execute_add_sfot_rewards(deps, info, pool_id, rewards),
+        ExecuteMsg::RegisterSfotStage { merkle_root, sfot_token_address, total_amount, start, expiration } => execute_register_sfot_stage(deps, env, info, merkle_root, sfot_token_address, total_amount, start, expiration),
+        ExecuteMsg::ClaimSfot { stage, amount, proof } => try_claim_sfot(deps, env, info, stage, amount, proof),
+        ExecuteMsg::ReclaimSfot { stage } => execute_reclaim_sfot(deps, env, info, stage),
+        ExecuteMsg::SetTerm { pool_id, term } => execute_set_term(deps, info, pool_id, term),
+        ExecuteMsg::Redeem { pool_id, id } => try_redeem(deps, env, info, pool_id, id),
+        ExecuteMsg::SetEmissionSchedule { pool_id, schedule } => execute_set_emission_schedule(deps, env, info, pool_id, schedule),
+        ExecuteMsg::SetVesting { pool_id, vesting } => execute_set_vesting(deps, info, pool_id, vesting),
+        ExecuteMsg::WithdrawVested { pool_id, early, recipient } => try_withdraw_vested(deps, env, info, pool_id, early, recipient),
+        ExecuteMsg::SetLoyalty { pool_id, loyalty } => execute_set_loyalty(deps, info, pool_id, loyalty),
+        ExecuteMsg::SetBoost { pool_id, boost } => execute_set_boost(deps, info, pool_id, boost),
+        ExecuteMsg::SetFee { pool_id, fee_bps, treasury } => execute_set_fee(deps, info, pool_id, fee_bps, treasury),
+        ExecuteMsg::CollectFees { pool_id } => try_collect_fees(deps, info, pool_id),
+        ExecuteMsg::ReceiveNft(msg) => try_receive_nft(deps, env, info, msg),
+        ExecuteMsg::UnlockBfot { pool_id, amount } => try_unlock_bfot(deps, env, info, pool_id, amount),
+        ExecuteMsg::UnlockNft { pool_id, collection, token_id } => try_unlock_nft(deps, env, info, pool_id, collection, token_id),
     }
 }
 pub fn check_enabled(
     deps: &DepsMut,
-    info: &MessageInfo
+    _info: &MessageInfo,
+    pool_id: u64
 ) -> Result<Response, ContractError> {
-    let cfg = CONFIG.load(deps.storage)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
     if !cfg.enabled {
         return Err(ContractError::Disabled {})
     }
     Ok(Response::new().add_attribute("action", "check_enabled"))
 }
 
+/// Resolves the staker a message acts on. Without `staker` (or with the sender's own
+/// address) this is the sender; otherwise the sender must be an unexpired operator of it.
+pub fn resolve_staker(
+    deps: &DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    staker: Option<String>
+) -> Result<Addr, ContractError> {
+    let staker = match staker {
+        Some(staker) => deps.api.addr_validate(&staker)?,
+        None => return Ok(info.sender.clone())
+    };
+    if staker == info.sender {
+        return Ok(staker);
+    }
+
+    let expires = OPERATORS.may_load(deps.storage, (staker.clone(), info.sender.clone()))?
+        .ok_or(ContractError::Unauthorized {})?;
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+    Ok(staker)
+}
+
 pub fn update_reward (
     storage: &mut dyn Storage,
-    api: &dyn Api,
     env: Env,
-    address: Addr,
-    start_after:Option<String>
+    pool_id: u64,
+    address: Addr
 ) -> Result<Response, ContractError> {
-    
-    let exists = STAKERS.may_load(storage, address.clone())?;
-    let (mut amount, mut reward, mut last_time, mut sfot_reward) = (Uint128::zero(), Uint128::zero(), 0u64, Uint128::zero());
-    if exists.is_some() {
-        (amount, reward, last_time, sfot_reward) = exists.unwrap();
+    if pool_id == 0 {
+        migrate_legacy_staker(storage, &env, &address)?;
     }
+    let state = update_global_reward(storage, &env, pool_id)?;
 
-    if last_time == 0u64 {
-        last_time = env.block.time.seconds();
+    // without a row there is nothing to settle, `save_staker` starts its index when one is saved
+    let (amount, mut reward, last_time, sfot_reward) = match stakers().may_load(storage, (pool_id, address.clone()))? {
+        Some(staker) => staker,
+        None => return Ok(Response::default())
+    };
+    let paid = REWARD_INDEX.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    let now = env.block.time.seconds();
+    let stake_age = STAKE_AGES.may_load(storage, (pool_id, address.clone()))?;
+    let counted = stake_age.as_ref().map_or(Decimal::one(), |age| age.multiplier);
+    // the period since the last settlement earns at its average multiplier, the part of it
+    // beyond the bonus weight the pool counted is owed on top of the emission
+    let multiplier = match &stake_age {
+        Some(age) => {
+            let cfg = POOLS.load(storage, pool_id)?;
+            average_multiplier(storage, &cfg, pool_id, &address, age.since, last_time, now)?
+        },
+        None => counted
+    };
+    let earned = earned_reward(amount + weight_bonus(amount, multiplier), paid, state.reward_per_token)?;
+    let counted_earned = earned_reward(amount + weight_bonus(amount, counted), paid, state.reward_per_token)?;
+    if earned != counted_earned {
+        update_reward_state(storage, pool_id, |state| {
+            state.liabilities = (state.liabilities + earned).saturating_sub(counted_earned);
+        })?;
     }
-    
-    STAKERS.save(storage, address.clone(), &(amount, reward, last_time, sfot_reward))?;
+    reward += earned;
 
-    let cfg = CONFIG.load(storage)?;
-    let delta = (env.block.time.seconds() + cfg.delta_time) / cfg.reward_interval - (last_time + cfg.delta_time) / cfg.reward_interval;
-    
-    if cfg.gfot_amount > Uint128::zero() && amount > Uint128::zero() && delta > 0 {
-        reward += cfg.daily_fot_amount * Uint128::from(delta) * amount / cfg.gfot_amount;
-        STAKERS.save(storage, address.clone(), &(amount, reward, env.block.time.seconds(), sfot_reward))?;
+    stakers().save(storage, (pool_id, address.clone()), &(amount, reward, now, sfot_reward))?;
+    REWARD_INDEX.save(storage, (pool_id, address.clone()), &state.reward_per_token)?;
+
+    // rows saved before stake ages were tracked start aging on their first settlement
+    if !amount.is_zero() {
+        let since = stake_age.map_or(now, |age| age.since);
+        set_stake_age(storage, pool_id, address, amount, counted, since, now)?;
     }
 
     Ok(Response::default())
 }
 
-pub fn try_receive(
-    deps: DepsMut, 
-    env: Env,
-    info: MessageInfo, 
-    wrapper: Cw20ReceiveMsg
-) -> Result<Response, ContractError> {
-    check_enabled(&deps, &info)?;
-    let mut cfg = CONFIG.load(deps.storage)?;
-    
-    if wrapper.amount == Uint128::zero() {
-        return Err(ContractError::InvalidInput {});
+/// Weight of `amount` staked with `multiplier` on top of the amount itself
+fn weight_bonus(amount: Uint128, multiplier: Decimal) -> Uint128 {
+    (amount * multiplier).saturating_sub(amount)
+}
+
+/// Loyalty multiplier of a stake aging since `since`
+fn loyalty_multiplier(cfg: &Config, since: u64, now: u64) -> Decimal {
+    let loyalty = match &cfg.loyalty {
+        Some(loyalty) => loyalty,
+        None => return Decimal::one()
+    };
+    let days = now.saturating_sub(since) / 86400;
+    let multiplier = match &loyalty.curve {
+        LoyaltyCurve::Linear { per_day } => per_day
+            .checked_mul(Decimal::from_ratio(days, 1u64))
+            .and_then(|bonus| bonus.checked_add(Decimal::one()))
+            .unwrap_or(loyalty.cap),
+        LoyaltyCurve::Steps { steps } => steps.iter()
+            .take_while(|step| step.days <= days)
+            .last()
+            .map_or(Decimal::one(), |step| step.multiplier)
+    };
+    multiplier.min(loyalty.cap).max(Decimal::one())
+}
+
+/// Loyalty multiplier of a stake aging since `since`, averaged over [from, to)
+fn average_loyalty(cfg: &Config, since: u64, from: u64, to: u64) -> Decimal {
+    // rows saved before their first settlement carry no last_time
+    let from = from.max(since);
+    if to <= from {
+        return loyalty_multiplier(cfg, since, to);
+    }
+    let last = loyalty_multiplier(cfg, since, u64::MAX);
+    let mut total = Decimal::zero();
+    let mut time = from;
+    while time < to {
+        // the multiplier holds until the next whole day of age, for good once it stops growing
+        let multiplier = loyalty_multiplier(cfg, since, time);
+        let end = if multiplier == last {
+            to
+        } else {
+            (since + (time.saturating_sub(since) / 86400 + 1) * 86400).min(to)
+        };
+        total = total.saturating_add(multiplier.saturating_mul(Decimal::from_ratio(end - time, 1u64)));
+        time = end;
     }
-    let user_addr = &deps.api.addr_validate(&wrapper.sender)?;
+    total / Decimal::from_ratio(to - from, 1u64)
+}
 
-    // Staking case
-    if info.sender == cfg.gfot_token_address {
-        update_reward(deps.storage, deps.api, env, user_addr.clone(), None)?;
-        let (mut amount, mut reward, mut last_time, sfot_reward) = STAKERS.load(deps.storage, user_addr.clone())?;
-        amount += wrapper.amount;
-        STAKERS.save(deps.storage, user_addr.clone(), &(amount, reward, last_time, sfot_reward))?;
-        
-        cfg.gfot_amount = cfg.gfot_amount + wrapper.amount;
-        CONFIG.save(deps.storage, &cfg)?;
+/// Multiplier of a staker aging since `since` at `now`: its loyalty times its boost
+fn stake_multiplier(
+    storage: &dyn Storage,
+    cfg: &Config,
+    pool_id: u64,
+    address: &Addr,
+    since: u64,
+    now: u64
+) -> StdResult<Decimal> {
+    average_multiplier(storage, cfg, pool_id, address, since, now, now)
+}
 
-        return Ok(Response::new()
-            .add_attributes(vec![
-                attr("action", "stake"),
-                attr("address", user_addr),
-                attr("amount", wrapper.amount)
-            ]));
+/// Same as `stake_multiplier` with the loyalty averaged over [from, to). The boost lock only
+/// changes when the staker is settled, so it holds over the period.
+fn average_multiplier(
+    storage: &dyn Storage,
+    cfg: &Config,
+    pool_id: u64,
+    address: &Addr,
+    since: u64,
+    from: u64,
+    to: u64
+) -> StdResult<Decimal> {
+    let lock = BOOST_LOCKS.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    Ok(average_loyalty(cfg, since, from, to) * boost_multiplier(cfg, &lock))
+}
 
-    } else if info.sender == cfg.fot_token_address {
-        //Just receive in contract cache and update config
-        cfg.fot_amount = cfg.fot_amount + wrapper.amount;
-        CONFIG.save(deps.storage, &cfg)?;
+/// Boost multiplier of the bFOT and NFTs in `lock`
+fn boost_multiplier(cfg: &Config, lock: &BoostLock) -> Decimal {
+    match &cfg.boost {
+        Some(boost) => uncapped_boost(boost, lock).map_or(boost.cap, |multiplier| multiplier.min(boost.cap)),
+        None => Decimal::one()
+    }
+}
 
-        return Ok(Response::new()
-            .add_attributes(vec![
-                attr("action", "fund"),
-                attr("address", user_addr),
-                attr("amount", wrapper.amount),
-            ]));
+/// Boost multiplier of `lock` before the cap, None if it overflows
+fn uncapped_boost(boost: &Boost, lock: &BoostLock) -> Option<Decimal> {
+    let nft_boost = lock.nfts.iter()
+        .filter_map(|nft| boost.collections.iter().find(|c| c.address == nft.collection))
+        .fold(Decimal::zero(), |total, collection| total.saturating_add(collection.boost));
+    Decimal::checked_from_ratio(lock.bfot / boost.bfot_step, 1u128).ok()
+        .and_then(|steps| steps.checked_mul(boost.bfot_boost).ok())
+        .and_then(|bfot_boost| bfot_boost.checked_add(nft_boost).ok())
+        .and_then(|total| total.checked_add(Decimal::one()).ok())
+}
 
-    } else {
-        return Err(ContractError::UnacceptableToken {})
+/// Rejects a lock whose boost would go past the cap
+fn check_boost_cap(boost: &Boost, lock: &BoostLock) -> Result<(), ContractError> {
+    match uncapped_boost(boost, lock) {
+        Some(multiplier) if multiplier <= boost.cap => Ok(()),
+        _ => Err(ContractError::BoostCapped {})
     }
 }
 
-pub fn try_claim_reward(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo
-) -> Result<Response, ContractError> {
+/// Moves a settled staker's stake age to `since` with the multiplier due at `now`,
+/// keeping the pool's bonus weight in line. The staker must be settled first.
+fn set_stake_age(
+    storage: &mut dyn Storage,
+    pool_id: u64,
+    address: Addr,
+    amount: Uint128,
+    prev_multiplier: Decimal,
+    since: u64,
+    now: u64
+) -> StdResult<()> {
+    let cfg = POOLS.load(storage, pool_id)?;
+    let multiplier = stake_multiplier(storage, &cfg, pool_id, &address, since, now)?;
+    if multiplier != prev_multiplier {
+        let (prev, next) = (weight_bonus(amount, prev_multiplier), weight_bonus(amount, multiplier));
+        update_reward_state(storage, pool_id, |state| state.bonus_weight = (state.bonus_weight + next).saturating_sub(prev))?;
+    }
+    STAKE_AGES.save(storage, (pool_id, address), &StakeAge { since, multiplier })
+}
 
-    check_enabled(&deps, &info)?;
-    update_reward(deps.storage, deps.api, env.clone(), info.sender.clone(), None)?;
-    let mut cfg = CONFIG.load(deps.storage)?;
+/// Weight the emission is shared by: the staked amount, never less than the rewarded weight,
+/// and the loyalty and boost bonus of stakers. Unbonding stake dilutes the emission without
+/// earning it; stake the owner withdrew does neither.
+fn reward_weight(cfg: &Config, state: &RewardState) -> Uint128 {
+    cfg.staked_amount.max(state.total_weight) + state.bonus_weight
+}
 
-    let (amount, reward, last_time, sfot_reward) = STAKERS.load(deps.storage, info.sender.clone())?;
-    
-    if reward == Uint128::zero() {
-        return Err(ContractError::NoReward {});
+/// Advances the reward index of the pool to now. Must run before the staked amount, the reward
+/// constants or the rewarded weight change.
+pub fn update_global_reward(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_id: u64
+) -> StdResult<RewardState> {
+    let cfg = POOLS.load(storage, pool_id)?;
+    let state = accrue_global_reward(&cfg, REWARD_STATE.load(storage, pool_id)?, env.block.time.seconds())?;
+    REWARD_STATE.save(storage, pool_id, &state)?;
+    Ok(state)
+}
+
+/// Reward state with the emission of the reward intervals since last_update accrued
+fn accrue_global_reward(cfg: &Config, mut state: RewardState, now: u64) -> StdResult<RewardState> {
+    let emitted = emitted_between(cfg, state.last_update, now)?;
+    let weight = reward_weight(cfg, &state);
+    if !emitted.is_zero() && !weight.is_zero() {
+        state.reward_per_token += Decimal256::from_ratio(emitted, weight);
+        state.liabilities += emitted.multiply_ratio(state.total_weight + state.bonus_weight, weight);
     }
-    if cfg.fot_amount < reward {
-        return Err(ContractError::NotEnoughFOT {});
+    state.last_update = now;
+    Ok(state)
+}
+
+/// Emission of the reward intervals ending in (from, to], each at the rate of its last second
+fn emitted_between(cfg: &Config, from: u64, to: u64) -> StdResult<Uint128> {
+    let pieces = emission_pieces(cfg, from, to)?;
+    let mut emitted = Uint128::zero();
+    for (i, (start, rate)) in pieces.iter().enumerate() {
+        // an interval ending on a piece's start time was earned before it
+        let end = pieces.get(i + 1).map_or(to, |(next, _)| *next);
+        emitted += rate.checked_mul(Uint128::from(reward_intervals(cfg, *start, end)))?;
     }
+    Ok(emitted)
+}
 
-   
-    
-    cfg.fot_amount -= Uint128::from(reward);
-    CONFIG.save(deps.storage, &cfg)?;
-    
-    // if amount == Uint128::zero() {
-        // STAKERS.remove(deps.storage, info.sender.clone());
-    // } else {
-        STAKERS.save(deps.storage, info.sender.clone(), &(amount, Uint128::zero(), last_time, sfot_reward))?;
-    // }
+/// Emission per reward interval at `time`
+fn emission_rate(cfg: &Config, time: u64) -> StdResult<Uint128> {
+    Ok(emission_pieces(cfg, time, time + 1)?[0].1)
+}
 
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: cfg.fot_token_address.clone().into(),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: info.sender.clone().into(),
-            amount: Uint128::from(reward),
-        })?,
-        funds: vec![],
-    };
+/// (start_time, rate) pieces of the emission over [from, to), the first starting at `from`
+fn emission_pieces(cfg: &Config, from: u64, to: u64) -> StdResult<Vec<(u64, Uint128)>> {
+    let mut pieces = vec![(from, cfg.daily_fot_amount)];
+    match &cfg.emission {
+        None => {},
+        Some(EmissionSchedule::Segments { segments }) => {
+            for segment in segments.iter().take_while(|segment| segment.start_time < to) {
+                if segment.start_time <= from {
+                    pieces = vec![(from, segment.rate)];
+                } else {
+                    pieces.push((segment.start_time, segment.rate));
+                }
+            }
+        },
+        Some(EmissionSchedule::Decay { start_time, initial_rate, period, factor }) => {
+            let mut k = from.saturating_sub(*start_time) / period;
+            let mut start = start_time + k * period;
+            while start < to {
+                let rate = decayed_rate(*initial_rate, *factor, k);
+                if start <= from {
+                    pieces = vec![(from, rate)];
+                } else {
+                    pieces.push((start, rate));
+                }
+                // nothing changes once decayed to zero
+                if rate.is_zero() {
+                    break;
+                }
+                k += 1;
+                start += period;
+            }
+        }
+    }
+    Ok(pieces)
+}
 
-    
-    return Ok(Response::new()
-        .add_message(exec_cw20_transfer)
-        .add_attributes(vec![
-            attr("action", "claim_reward"),
-            attr("address", info.sender.clone()),
-            attr("fot_amount", Uint128::from(reward)),
-        ]));
+/// `initial_rate` after `k` decay periods
+fn decayed_rate(initial_rate: Uint128, factor: Decimal, k: u64) -> Uint128 {
+    u32::try_from(k).ok()
+        .and_then(|k| factor.checked_pow(k).ok())
+        .map_or(Uint128::zero(), |decay| initial_rate * decay)
 }
 
-pub fn try_create_unstake(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    unstake_amount: Uint128
-) -> Result<Response, ContractError> {
+fn validate_emission_schedule(schedule: EmissionSchedule, reward_interval: u64) -> StdResult<EmissionSchedule> {
+    match &schedule {
+        EmissionSchedule::Segments { segments } => {
+            if segments.windows(2).any(|pair| pair[0].start_time >= pair[1].start_time) {
+                return Err(StdError::generic_err("Segment start times must ascend"));
+            }
+        },
+        EmissionSchedule::Decay { initial_rate, period, factor, .. } => {
+            if *period == 0 || *factor >= Decimal::one() {
+                return Err(StdError::generic_err("Decay needs a period and a factor below one"));
+            }
+            if *period < reward_interval {
+                return Err(StdError::generic_err("Decay period must be at least the reward interval"));
+            }
+            if !decayed_rate(*initial_rate, *factor, MAX_DECAY_PERIODS).is_zero() {
+                return Err(StdError::generic_err(format!("Decay must reach zero within {} periods", MAX_DECAY_PERIODS)));
+            }
+        }
+    }
+    Ok(schedule)
+}
 
-    check_enabled(&deps, &info)?;
-    update_reward(deps.storage, deps.api, env.clone(), info.sender.clone(), None)?;
-    let mut cfg = CONFIG.load(deps.storage)?;
-    let (amount, reward, last_time, sfot_reward) = STAKERS.load(deps.storage, info.sender.clone())?;
-    
-    if amount == Uint128::zero() {
-        return Err(ContractError::NoStaked {});
+/// FOT earned by `amount` of staked gFOT since it was settled at index `paid`
+fn earned_reward(amount: Uint128, paid: Decimal256, reward_per_token: Decimal256) -> StdResult<Uint128> {
+    let earned = Uint256::from(amount) * (reward_per_token - paid);
+    Uint128::try_from(earned).map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+/// Legacy per-staker accrual, used to settle rewards saved before the reward index existed
+fn legacy_accrued_reward(cfg: &Config, amount: Uint128, last_time: u64, now: u64) -> Uint128 {
+    let delta = reward_intervals(cfg, last_time, now);
+    if cfg.staked_amount > Uint128::zero() && amount > Uint128::zero() && delta > 0 {
+        cfg.daily_fot_amount * Uint128::from(delta) * amount / cfg.staked_amount
+    } else {
+        Uint128::zero()
     }
-    if cfg.gfot_amount < amount {
-        return Err(ContractError::NotEnoughgFOT {});
+}
+
+/// Moves a staker and unstaking list of the single pool contract into pool 0, settling the
+/// reward accrued per staker up to the migration and by the index since. The single pool
+/// contract kept no stake ages, the stake ages from the migration on.
+fn migrate_legacy_staker(storage: &mut dyn Storage, env: &Env, address: &Addr) -> StdResult<()> {
+    let migration = match LEGACY_MIGRATION.may_load(storage)? {
+        Some(migration) => migration,
+        None => return Ok(())
+    };
+    if let Some((amount, reward, last_time, sfot_reward)) = LEGACY_STAKERS.may_load(storage, address.clone())? {
+        let cfg = CONFIG.load(storage)?;
+        let state = update_global_reward(storage, env, 0)?;
+        let now = env.block.time.seconds();
+        let reward = reward + legacy_accrued_reward(&cfg, amount, last_time, migration.time)
+            + earned_reward(amount, Decimal256::zero(), state.reward_per_token)?;
+        stakers().save(storage, (0, address.clone()), &(amount, reward, now, sfot_reward))?;
+        REWARD_INDEX.save(storage, (0, address.clone()), &state.reward_per_token)?;
+        update_reward_state(storage, 0, |state| state.liabilities += reward)?;
+        set_staked_balance(storage, env.block.height, 0, address.clone(), amount)?;
+        set_stake_age(storage, 0, address.clone(), amount, Decimal::one(), migration.time, now)?;
+        LEGACY_STAKERS.remove(storage, address.clone());
     }
+    if let Some(list) = LEGACY_UNSTAKING.may_load(storage, address.clone())? {
+        update_stats(storage, 0, |stats| stats.unbonding_count += list.len() as u64)?;
+        let mut unstaking = list;
+        unstaking.extend(UNSTAKING.may_load(storage, (0, address.clone()))?.unwrap_or_default());
+        UNSTAKING.save(storage, (0, address.clone()), &unstaking)?;
+        LEGACY_UNSTAKING.remove(storage, address.clone());
+    }
+    Ok(())
+}
 
-    if amount < unstake_amount {
-        return Err(ContractError::NotEnoughgFOT {});
+/// Same as `migrate_legacy_staker` for a position of pool 0 last settled before the migration
+fn migrate_legacy_position(storage: &mut dyn Storage, env: &Env, position: &mut Position) -> StdResult<()> {
+    let migration = match LEGACY_MIGRATION.may_load(storage)? {
+        Some(migration) => migration,
+        None => return Ok(())
+    };
+    if position.pool_id != 0 || position.last_time >= migration.time {
+        return Ok(());
     }
+    let cfg = CONFIG.load(storage)?;
+    let state = update_global_reward(storage, env, 0)?;
+    position.reward += legacy_accrued_reward(&cfg, position.amount, position.last_time, migration.time)
+        + earned_reward(position.amount, Decimal256::zero(), state.reward_per_token)?;
+    position.last_time = env.block.time.seconds();
+    position.reward_index = state.reward_per_token;
+    update_reward_state(storage, 0, |state| {
+        state.total_weight += position.amount;
+        state.liabilities += position.reward;
+    })
+}
 
-    let mut exists = UNSTAKING.may_load(deps.storage, info.sender.clone())?;
-    let mut unstaking = vec![];
-    if exists.is_some() {
-        unstaking = exists.unwrap();
-    } 
+fn update_reward_state<F: FnOnce(&mut RewardState)>(storage: &mut dyn Storage, pool_id: u64, action: F) -> StdResult<()> {
+    let mut state = REWARD_STATE.load(storage, pool_id)?;
+    action(&mut state);
+    REWARD_STATE.save(storage, pool_id, &state)
+}
 
-    unstaking.push((unstake_amount, env.block.time.seconds() + cfg.lock_days * 86400u64));
-    UNSTAKING.save(deps.storage, info.sender.clone(), &unstaking)?;
+/// Saves a staker and keeps the staked balance snapshots in line with its amount. A new
+/// staker is settled at the current reward index, its stake ages from the first gFOT in.
+/// Returns the member diff to report to hooks if the amount changed and hooks are registered.
+pub fn save_staker(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_id: u64,
+    address: Addr,
+    staker: &StakerData
+) -> StdResult<Option<MemberDiff>> {
+    if stakers().may_load(storage, (pool_id, address.clone()))?.is_none() {
+        let state = REWARD_STATE.load(storage, pool_id)?;
+        REWARD_INDEX.save(storage, (pool_id, address.clone()), &state.reward_per_token)?;
+    }
+    stakers().save(storage, (pool_id, address.clone()), staker)?;
+    let diff = set_staked_balance(storage, env.block.height, pool_id, address.clone(), staker.0)?;
 
-    STAKERS.save(deps.storage, info.sender.clone(), &(amount - unstake_amount, reward, last_time, sfot_reward))?;
+    let now = env.block.time.seconds();
+    if staker.0.is_zero() {
+        STAKE_AGES.remove(storage, (pool_id, address));
+    } else if STAKE_AGES.may_load(storage, (pool_id, address.clone()))?.is_none() {
+        set_stake_age(storage, pool_id, address, staker.0, Decimal::one(), now, now)?;
+    }
+    Ok(diff)
+}
 
-    return Ok(Response::new()
-        .add_attributes(vec![
-            attr("action", "create_unstake"),
-            attr("address", info.sender.clone()),
-            attr("gfot_amount", Uint128::from(amount)),
-        ]));
+/// Removes a staker, forfeiting its unclaimed reward and IOU. Settle it with `update_reward` first.
+pub fn remove_staker(
+    storage: &mut dyn Storage,
+    height: u64,
+    pool_id: u64,
+    address: Addr
+) -> StdResult<Option<MemberDiff>> {
+    let reward = stakers().may_load(storage, (pool_id, address.clone()))?
+        .map(|(_, reward, _, _)| reward)
+        .unwrap_or_default();
+    let iou = IOUS.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    update_reward_state(storage, pool_id, |state| state.liabilities = state.liabilities.saturating_sub(reward + iou))?;
+    set_iou(storage, pool_id, address.clone(), Uint128::zero())?;
+    stakers().remove(storage, (pool_id, address.clone()))?;
+    REWARD_INDEX.remove(storage, (pool_id, address.clone()));
+    let diff = set_staked_balance(storage, height, pool_id, address.clone(), Uint128::zero())?;
+    STAKE_AGES.remove(storage, (pool_id, address));
+    Ok(diff)
 }
 
-pub fn try_fetch_unstake(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    index: u64
-) -> Result<Response, ContractError> {
+fn set_staked_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    pool_id: u64,
+    address: Addr,
+    amount: Uint128
+) -> StdResult<Option<MemberDiff>> {
+    let positions = POSITION_STAKES.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    let balance = STAKED_BALANCES.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    let prev = balance - positions;
+    if prev == amount {
+        return Ok(None);
+    }
+    let multiplier = STAKE_AGES.may_load(storage, (pool_id, address.clone()))?
+        .map_or(Decimal::one(), |age| age.multiplier);
+    update_reward_state(storage, pool_id, |state| {
+        state.total_weight = state.total_weight + amount - prev;
+        state.bonus_weight = (state.bonus_weight + weight_bonus(amount, multiplier))
+            .saturating_sub(weight_bonus(prev, multiplier));
+    })?;
+
+    if prev.is_zero() || amount.is_zero() {
+        update_stats(storage, pool_id, |stats| {
+            if prev.is_zero() {
+                stats.staker_count += 1;
+            } else {
+                stats.staker_count = stats.staker_count.saturating_sub(1);
+            }
+        })?;
+    }
 
-    check_enabled(&deps, &info)?;
-    update_reward(deps.storage, deps.api, env.clone(), info.sender.clone(), None)?;
+    snapshot_balance(storage, height, pool_id, address, balance, positions + amount)
+}
 
-    let mut cfg = CONFIG.load(deps.storage)?;
-    
-    let exist = UNSTAKING.may_load(deps.storage, info.sender.clone())?;
-    let mut list = vec![];
-    if exist.is_some() {
-        list = exist.unwrap();
+/// Sets the position stakes counted for `owner` in the staked balance snapshots
+fn set_position_stake(
+    storage: &mut dyn Storage,
+    height: u64,
+    pool_id: u64,
+    owner: Addr,
+    amount: Uint128
+) -> StdResult<Option<MemberDiff>> {
+    let prev = POSITION_STAKES.may_load(storage, (pool_id, owner.clone()))?.unwrap_or_default();
+    if prev == amount {
+        return Ok(None);
+    }
+    if amount.is_zero() {
+        POSITION_STAKES.remove(storage, (pool_id, owner.clone()));
     } else {
-        return Err(ContractError::NotCreatedUnstaking {});
+        POSITION_STAKES.save(storage, (pool_id, owner.clone()), &amount)?;
     }
-    
-    if (list.len() as u64) <= index {
-        return Err(ContractError::NotCreatedUnstaking {});
+    let balance = STAKED_BALANCES.may_load(storage, (pool_id, owner.clone()))?.unwrap_or_default();
+    snapshot_balance(storage, height, pool_id, owner, balance, balance + amount - prev)
+}
+
+/// Moves the amount of `position` in the staked balance snapshots from its current owner
+/// to `owner`, dropping it if None
+fn move_position_stake(
+    storage: &mut dyn Storage,
+    height: u64,
+    position: &mut Position,
+    owner: Option<Addr>
+) -> StdResult<Vec<MemberDiff>> {
+    let pool_id = position.pool_id;
+    let mut diffs = vec![];
+    if position.owner == owner {
+        return Ok(diffs);
     }
-    let (mut amount, mut timestamp) = list.get(index as usize).unwrap();
+    if let Some(prev) = position.owner.take() {
+        let stake = POSITION_STAKES.load(storage, (pool_id, prev.clone()))?;
+        diffs.extend(set_position_stake(storage, height, pool_id, prev, stake - position.amount)?);
+    }
+    if let Some(next) = owner.clone() {
+        let stake = POSITION_STAKES.may_load(storage, (pool_id, next.clone()))?.unwrap_or_default();
+        diffs.extend(set_position_stake(storage, height, pool_id, next, stake + position.amount)?);
+    }
+    position.owner = owner;
+    Ok(diffs)
+}
 
-    if cfg.gfot_amount < amount {
-        return Err(ContractError::NotEnoughgFOT {  });
+/// Checkpoints the staked balance of `address` and the pool total
+fn snapshot_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    pool_id: u64,
+    address: Addr,
+    prev: Uint128,
+    balance: Uint128
+) -> StdResult<Option<MemberDiff>> {
+    if balance.is_zero() {
+        STAKED_BALANCES.remove(storage, (pool_id, address.clone()), height)?;
+    } else {
+        STAKED_BALANCES.save(storage, (pool_id, address.clone()), &balance, height)?;
     }
-    if timestamp > env.block.time.seconds() {
-        return Err(ContractError::StillLocked {});
+    let total = STAKED_TOTAL.may_load(storage, pool_id)?.unwrap_or_default();
+    STAKED_TOTAL.save(storage, pool_id, &(total + balance - prev), height)?;
+
+    // the diff is only reported to hooks
+    if HOOKS.may_load(storage, pool_id)?.unwrap_or_default().is_empty() {
+        return Ok(None);
     }
-    cfg.gfot_amount -= amount;
-    CONFIG.save(deps.storage, &cfg)?;
-    
-    list.remove(index as usize);
-    UNSTAKING.save(deps.storage, info.sender.clone(), &list)?;
+    Ok(Some(MemberDiff {
+        key: address.into(),
+        old: member_weight(prev),
+        new: member_weight(balance)
+    }))
+}
 
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: cfg.gfot_token_address.clone().into(),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: info.sender.clone().into(),
-            amount: Uint128::from(amount),
-        })?,
-        funds: vec![],
-    };
-    
-    return Ok(Response::new()
-        .add_message(exec_cw20_transfer)
-        .add_attributes(vec![
-            attr("action", "fetch_unstake"),
-            attr("address", info.sender.clone()),
-            attr("gfot_amount", Uint128::from(amount)),
-        ]));
+pub fn update_stats<F: FnOnce(&mut Stats)>(storage: &mut dyn Storage, pool_id: u64, action: F) -> StdResult<()> {
+    let mut stats = STATS.may_load(storage, pool_id)?.unwrap_or_default();
+    action(&mut stats);
+    STATS.save(storage, pool_id, &stats)
 }
 
-pub fn check_owner(
-    deps: &DepsMut,
-    info: &MessageInfo
-) -> Result<Response, ContractError> {
-    let cfg = CONFIG.load(deps.storage)?;
-    let owner = cfg.owner.ok_or(ContractError::Unauthorized {})?;
-    if info.sender != owner {
-        return Err(ContractError::Unauthorized {})
+/// cw4 weight of a staked amount, None if nothing staked. Saturates at u64::MAX.
+fn member_weight(amount: Uint128) -> Option<u64> {
+    if amount.is_zero() {
+        return None;
     }
-    Ok(Response::new().add_attribute("action", "check_owner"))
+    Some(u64::try_from(amount.u128()).unwrap_or(u64::MAX))
 }
 
-pub fn execute_update_config(
-    deps: DepsMut,
-    info: MessageInfo,
-    new_owner: Option<String>,
-) -> Result<Response, ContractError> {
-    // authorize owner
-    check_owner(&deps, &info)?;
-    
-    //test code for checking if check_owner works well
-    // return Err(ContractError::InvalidInput {});
-    // if owner some validated to addr, otherwise set to none
-    let mut tmp_owner = None;
-    if let Some(addr) = new_owner {
-        tmp_owner = Some(deps.api.addr_validate(&addr)?)
+/// Builds a MemberChangedHookMsg submessage per registered hook
+fn member_changed_hooks(
+    storage: &dyn Storage,
+    pool_id: u64,
+    diffs: Vec<MemberDiff>
+) -> StdResult<Vec<SubMsg>> {
+    if diffs.is_empty() {
+        return Ok(vec![]);
     }
+    let msg = to_json_binary(&MemberChangedExecuteMsg::MemberChangedHook(MemberChangedHookMsg { diffs }))?;
+    let hooks = HOOKS.may_load(storage, pool_id)?.unwrap_or_default();
+    Ok(hooks
+        .into_iter()
+        .map(|hook| SubMsg::new(WasmMsg::Execute {
+            contract_addr: hook.into(),
+            msg: msg.clone(),
+            funds: vec![],
+        }))
+        .collect())
+}
 
-    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
-        exists.owner = tmp_owner;
-        Ok(exists)
+/// Number of reward intervals passed between `last_time` and `now`
+fn reward_intervals(cfg: &Config, last_time: u64, now: u64) -> u64 {
+    (now + cfg.delta_time) / cfg.reward_interval - (last_time + cfg.delta_time) / cfg.reward_interval
+}
+
+/// Same settlement as `update_reward`, applied to a single position
+pub fn update_position_reward (
+    storage: &mut dyn Storage,
+    env: &Env,
+    id: u64
+) -> Result<Position, ContractError> {
+    let mut position = positions().load(storage, id)?;
+    migrate_legacy_position(storage, env, &mut position)?;
+    let state = update_global_reward(storage, env, position.pool_id)?;
+    position.reward += earned_reward(position.amount, position.reward_index, state.reward_per_token)?;
+    position.reward_index = state.reward_per_token;
+    position.last_time = env.block.time.seconds();
+    positions().save(storage, id, &position)?;
+    Ok(position)
+}
+
+fn parse_token_id(token_id: &str) -> Result<u64, ContractError> {
+    token_id.parse::<u64>().map_err(|_| ContractError::InvalidInput {})
+}
+
+fn query_position_owner(deps: Deps, nft: &Addr, token_id: &str) -> StdResult<Addr> {
+    let res: Cw721OwnerOfResponse = deps.querier.query_wasm_smart(nft, &Cw721QueryMsg::OwnerOf {
+        token_id: token_id.to_string(),
+        include_expired: None
     })?;
+    deps.api.addr_validate(&res.owner)
+}
 
-    Ok(Response::new().add_attribute("action", "update_config"))
+pub fn try_receive(
+    deps: DepsMut, 
+    env: Env,
+    info: MessageInfo, 
+    wrapper: Cw20ReceiveMsg
+) -> Result<Response, ContractError> {
+    // A message that is not a ReceiveMsg targets pool 0, as before pools existed
+    let msg = from_json::<ReceiveMsg>(&wrapper.msg).ok();
+    let pool_id = match msg {
+        Some(ReceiveMsg::Stake { pool_id }) | Some(ReceiveMsg::InitialFund { pool_id }) | Some(ReceiveMsg::Boost { pool_id }) => pool_id,
+        Some(ReceiveMsg::UnstakePosition { .. }) => return Err(ContractError::InvalidInput {}),
+        None => 0
+    };
+    check_enabled(&deps, &info, pool_id)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let user_addr = deps.api.addr_validate(&wrapper.sender)?;
+
+    // An empty message stakes the stake token and funds with the FOT token
+    match msg {
+        Some(ReceiveMsg::Stake { .. }) | None if cfg.stake_token.is_token(&info.sender)
+            => stake(deps, env, pool_id, cfg, user_addr, wrapper.amount),
+        Some(ReceiveMsg::InitialFund { .. }) | None if info.sender == cfg.fot_token_address && cfg.reward_denom.is_none()
+            => fund_rewards(deps, env, pool_id, cfg, user_addr, wrapper.amount),
+        Some(ReceiveMsg::Boost { .. }) if info.sender == cfg.bfot_token_address
+            => lock_bfot(deps, env, pool_id, cfg, user_addr, wrapper.amount),
+        _ => Err(ContractError::UnacceptableToken {})
+    }
 }
 
-pub fn execute_update_constants(
+/// Stakes the native stake token sent along
+pub fn try_stake(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    daily_fot_amount: Uint128,
-    apy_prefix: Uint128,
-    reward_interval: u64,
-    delta_time: u64,
-    lock_days: u64,
-    enabled: bool
+    pool_id: u64
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    check_owner(&deps, &info)?;
-    
-    //test code for checking if check_owner works well
-    // return Err(ContractError::InvalidInput {});
-    // if owner some validated to addr, otherwise set to none
-    
-    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
-        exists.daily_fot_amount = daily_fot_amount;
-        exists.apy_prefix = apy_prefix;
-        exists.reward_interval = reward_interval;
-        exists.lock_days = lock_days;
-        exists.delta_time = delta_time;
-        exists.enabled = enabled;
-        Ok(exists)
-    })?;
+    check_enabled(&deps, &info, pool_id)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let amount = match &cfg.stake_token {
+        AssetInfo::NativeToken { denom } => must_pay(&info, denom)?,
+        AssetInfo::Token { .. } => return Err(ContractError::UnacceptableToken {})
+    };
 
-    Ok(Response::new().add_attribute("action", "update_constants"))
+    stake(deps, env, pool_id, cfg, info.sender, amount)
 }
 
-pub fn execute_add_stakers(
+/// Adds the native reward denom sent along to the reward pool
+pub fn try_fund_rewards(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    stakers: Vec<StakerInfo>
+    pool_id: u64
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    check_owner(&deps, &info)?;
+    check_enabled(&deps, &info, pool_id)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let amount = match &cfg.reward_denom {
+        Some(denom) => must_pay(&info, denom)?,
+        None => return Err(ContractError::UnacceptableToken {})
+    };
 
-    for staker in stakers {
-        STAKERS.save(deps.storage, staker.address.clone(), &(staker.amount, staker.reward, staker.last_time, staker.sfot_reward))?;
-    }
-    
-    Ok(Response::new().add_attribute("action", "add_stakers"))
+    fund_rewards(deps, env, pool_id, cfg, info.sender, amount)
 }
 
-pub fn execute_add_sfot_rewards(
+fn stake(
     deps: DepsMut,
-    info: MessageInfo,
-    rewards: Vec<SfotRewardInfo>
+    env: Env,
+    pool_id: u64,
+    mut cfg: Config,
+    user_addr: Addr,
+    amount: Uint128
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    check_owner(&deps, &info)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidInput {});
+    }
 
-    for rec in rewards {
-        let (amount, reward, last_time, mut sfot_reward) = STAKERS.load(deps.storage, rec.address.clone())?;
+    if let Some(term) = cfg.term.clone() {
+        return deposit(deps, env, pool_id, cfg, user_addr, amount, term);
+    }
 
-        sfot_reward += rec.sfot_reward;
+    // Staking as a position NFT
+    if let Some(nft) = cfg.position_nft.clone() {
+        let state = update_global_reward(deps.storage, &env, pool_id)?;
+        let id = POSITION_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+        POSITION_COUNT.save(deps.storage, &id)?;
+        let mut position = Position {
+            amount,
+            lock_days: cfg.lock_days,
+            start_time: env.block.time.seconds(),
+            reward: Uint128::zero(),
+            last_time: env.block.time.seconds(),
+            reward_index: state.reward_per_token,
+            pool_id,
+            owner: None
+        };
+        let diffs = move_position_stake(deps.storage, env.block.height, &mut position, Some(user_addr.clone()))?;
+        positions().save(deps.storage, id, &position)?;
+        update_reward_state(deps.storage, pool_id, |state| state.total_weight += amount)?;
+
+        cfg.staked_amount += amount;
+        POOLS.save(deps.storage, pool_id, &cfg)?;
 
-        STAKERS.save(deps.storage, rec.address.clone(), &(amount, reward, last_time, sfot_reward))?;
+        let exec_cw721_mint = WasmMsg::Execute {
+            contract_addr: nft.into(),
+            msg: to_json_binary(&Cw721ExecuteMsg::Mint(Cw721MintMsg {
+                token_id: id.to_string(),
+                owner: user_addr.to_string(),
+                token_uri: None,
+                extension: None
+            }))?,
+            funds: vec![],
+        };
+
+        return Ok(Response::new()
+            .add_message(exec_cw721_mint)
+            .add_submessages(member_changed_hooks(deps.storage, pool_id, diffs)?)
+            .add_attributes(vec![
+                attr("action", "stake_position"),
+                attr("address", user_addr),
+                attr("token_id", id.to_string()),
+                attr("amount", amount)
+            ]));
     }
-    
-    Ok(Response::new().add_attribute("action", "add_sfot_rewards"))
+
+    update_reward(deps.storage, env.clone(), pool_id, user_addr.clone())?;
+    let (staked, reward, last_time, sfot_reward) = stakers().may_load(deps.storage, (pool_id, user_addr.clone()))?.unwrap_or_default();
+    let diff = save_staker(deps.storage, &env, pool_id, user_addr.clone(), &(staked + amount, reward, last_time, sfot_reward))?;
+
+    cfg.staked_amount += amount;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+
+    Ok(Response::new()
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, diff.into_iter().collect())?)
+        .add_attributes(vec![
+            attr("action", "stake"),
+            attr("address", user_addr),
+            attr("amount", amount)
+        ]))
 }
 
-pub fn execute_remove_staker(
+/// Locks `amount` for the term, reserving its reward from the reward pool
+fn deposit(
     deps: DepsMut,
-    info: MessageInfo,
-    address: Addr
+    env: Env,
+    pool_id: u64,
+    mut cfg: Config,
+    user_addr: Addr,
+    amount: Uint128,
+    term: Term
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    check_owner(&deps, &info)?;
-    
-    STAKERS.remove(deps.storage, address.clone());
-    
-    Ok(Response::new().add_attribute("action", "remove_staker"))
-}
+    let reward = amount * term.rate;
+    if cfg.fot_amount < reward {
+        return Err(ContractError::NotEnoughFOT {});
+    }
+    cfg.fot_amount -= reward;
+    cfg.reserved_amount += reward;
+    cfg.deposited_amount += amount;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
 
+    let id = DEPOSIT_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    DEPOSIT_COUNT.save(deps.storage, &id)?;
+    let maturity = env.block.time.seconds() + term.days * 86400u64;
+    DEPOSITS.save(deps.storage, (user_addr.clone(), id), &Deposit {
+        pool_id,
+        amount,
+        reward,
+        start_time: env.block.time.seconds(),
+        maturity
+    })?;
 
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "deposit"),
+            attr("address", user_addr),
+            attr("deposit_id", id.to_string()),
+            attr("amount", amount),
+            attr("reward", reward),
+            attr("maturity", maturity.to_string()),
+        ]))
+}
 
-pub fn execute_remove_all_stakers(
+pub fn try_redeem(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    start_after: Option<String>,
-    limit: Option<u32>
+    pool_id: u64,
+    id: u64
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    check_owner(&deps, &info)?;
-    
-    let addr = maybe_addr(deps.api, start_after)?;
-    let start = addr.map(|addr| Bound::exclusive(addr.as_ref()));
-    let stakers:StdResult<Vec<_>> = STAKERS
-        .range(deps.storage, start, None, Order::Ascending)
-        .map(|item| map_staker(item))
-        .collect();
 
-    if stakers.is_err() {
-        return Err(ContractError::Map2ListFailed {})
+    // matured deposits stay redeemable while the pool is disabled
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    let deposit = DEPOSITS.load(deps.storage, (info.sender.clone(), id))?;
+    if deposit.pool_id != pool_id {
+        return Err(ContractError::InvalidInput {});
     }
-    
-    for item in stakers.unwrap() {
-        STAKERS.remove(deps.storage, item.address.clone());
-        UNSTAKING.remove(deps.storage, item.address.clone());
+    if deposit.maturity > env.block.time.seconds() {
+        return Err(ContractError::StillLocked {});
     }
-    
-    Ok(Response::new().add_attribute("action", "remove_all_stakers"))
-}
 
-pub fn try_withdraw_fot(deps: DepsMut, env:Env, info: MessageInfo) -> Result<Response, ContractError> {
-    
-    check_owner(&deps, &info)?;
-    let mut cfg = CONFIG.load(deps.storage)?;
-    
-    let fot_amount = cfg.fot_amount;
-    cfg.fot_amount = Uint128::zero();
-    CONFIG.save(deps.storage, &cfg)?;
+    DEPOSITS.remove(deps.storage, (info.sender.clone(), id));
+    cfg.reserved_amount -= deposit.reward;
+    cfg.deposited_amount -= deposit.amount;
+    let reward = take_fee(deps.storage, pool_id, &mut cfg, deposit.reward)?;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+    update_stats(deps.storage, pool_id, |stats| stats.fot_claimed += reward)?;
 
-    // create transfer cw20 msg
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: cfg.fot_token_address.clone().into(),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: info.sender.clone().into(),
-            amount: fot_amount,
-        })?,
-        funds: vec![],
-    };
+    let mut msgs = vec![transfer_msg(stake_balance(&cfg, deposit.amount), info.sender.clone())?];
+    if !reward.is_zero() {
+        msgs.push(transfer_msg(reward_balance(&cfg, reward), info.sender.clone())?);
+    }
 
-    return Ok(Response::new()
-        .add_message(exec_cw20_transfer)
+    Ok(Response::new()
+        .add_messages(msgs)
         .add_attributes(vec![
-            attr("action", "fot_withdraw_all"),
-            attr("address", info.sender.clone()),
-            attr("fot_amount", fot_amount),
-        ]));
+            attr("action", "redeem"),
+            attr("address", info.sender),
+            attr("deposit_id", id.to_string()),
+            attr("amount", deposit.amount),
+            attr("fot_amount", reward),
+            attr("fee_amount", deposit.reward - reward),
+        ]))
 }
 
-pub fn try_withdraw_gfot(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
-    
-    check_owner(&deps, &info)?;
-
-    let mut cfg = CONFIG.load(deps.storage)?;
-    let gfot_amount = cfg.gfot_amount;
-    cfg.gfot_amount = Uint128::zero();
-
-    CONFIG.save(deps.storage, &cfg)?;
+fn fund_rewards(
+    deps: DepsMut,
+    env: Env,
+    pool_id: u64,
+    mut cfg: Config,
+    sender: Addr,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidInput {});
+    }
 
-    // create transfer cw20 msg
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: cfg.gfot_token_address.clone().into(),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: info.sender.clone().into(),
-            amount: gfot_amount,
-        })?,
-        funds: vec![],
-    };
+    //Just receive in contract cache and update config
+    cfg.fot_amount += amount;
+    update_stats(deps.storage, pool_id, |stats| stats.fot_funded += amount)?;
+    let (settle_msgs, settled) = settle_ious(deps.storage, &env, pool_id, &mut cfg)?;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
 
-    return Ok(Response::new()
-        .add_message(exec_cw20_transfer)
+    Ok(Response::new()
+        .add_messages(settle_msgs)
         .add_attributes(vec![
-            attr("action", "gfot_withdraw_all"),
-            attr("address", info.sender.clone()),
-            attr("gfot_amount", gfot_amount),
-        ]));
+            attr("action", "fund"),
+            attr("address", sender),
+            attr("amount", amount),
+            attr("iou_settled", settled),
+        ]))
 }
 
+/// Pays IOUs from the reward pool, a page of at most MAX_LIMIT of them per call starting after
+/// the last one paid. Each is paid the same share, the part of the page the pool covers; ClaimReward
+/// pays a staker's own IOU without waiting for its page.
+/// Returns the transfers and the total paid; the caller saves `cfg`.
+fn settle_ious(storage: &mut dyn Storage, env: &Env, pool_id: u64, cfg: &mut Config) -> StdResult<(Vec<CosmosMsg>, Uint128)> {
+    if cfg.fot_amount.is_zero() {
+        return Ok((vec![], Uint128::zero()));
+    }
+    // a page of IOUs after the last one settled, wrapping around to the first
+    let cursor = REWARD_STATE.load(storage, pool_id)?.iou_cursor;
+    let mut ious = IOUS
+        .prefix(pool_id)
+        .range(storage, cursor.clone().map(Bound::exclusive), None, Order::Ascending)
+        .take(MAX_LIMIT as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+    if let Some(cursor) = cursor {
+        let wrapped:StdResult<Vec<_>> = IOUS
+            .prefix(pool_id)
+            .range(storage, None, Some(Bound::inclusive(cursor)), Order::Ascending)
+            .take(MAX_LIMIT as usize - ious.len())
+            .collect();
+        ious.extend(wrapped?);
+    }
+    let total: Uint128 = ious.iter().map(|(_, iou)| *iou).sum();
+    let covered = cfg.fot_amount.min(total);
+    if covered.is_zero() {
+        return Ok((vec![], Uint128::zero()));
+    }
+
+    let mut msgs = vec![];
+    let mut settled = Uint128::zero();
+    let last = ious.last().map(|(address, _)| address.clone());
+    for (address, iou) in ious {
+        let paid = iou.multiply_ratio(covered, total);
+        if paid.is_zero() {
+            continue;
+        }
+        cfg.fot_amount -= paid;
+        settled += paid;
+        set_iou(storage, pool_id, address.clone(), iou - paid)?;
+        let net = take_fee(storage, pool_id, cfg, paid)?;
+        msgs.extend(pay_reward(storage, env, pool_id, cfg, address.clone(), address, net)?);
+    }
 
+    update_reward_state(storage, pool_id, |state| {
+        state.liabilities = state.liabilities.saturating_sub(settled);
+        state.iou_cursor = last;
+    })?;
+    Ok((msgs, settled))
+}
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Config {} 
-            => to_binary(&query_config(deps)?),
-        QueryMsg::Staker {address} 
-            => to_binary(&query_staker(deps, address)?),
-        QueryMsg::ListStakers {start_after, limit} 
-            => to_binary(&query_list_stakers(deps, start_after, limit)?),
-        QueryMsg::Apy {} 
-            => to_binary(&query_apy(deps)?),
-        QueryMsg::Unstaking {address} 
-            => to_binary(&query_unstaking(deps, address)?),
+/// Sets the IOU of `address`, keeping RewardState.ious in line
+fn set_iou(storage: &mut dyn Storage, pool_id: u64, address: Addr, iou: Uint128) -> StdResult<()> {
+    let prev = IOUS.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    if iou.is_zero() {
+        IOUS.remove(storage, (pool_id, address));
+    } else {
+        IOUS.save(storage, (pool_id, address), &iou)?;
     }
+    update_reward_state(storage, pool_id, |state| state.ious = (state.ious + iou).saturating_sub(prev))
 }
 
-pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
-    let cfg = CONFIG.load(deps.storage)?;
-    Ok(ConfigResponse {
-        owner: cfg.owner.map(|o| o.into()),
-        fot_token_address: cfg.fot_token_address.into(),
-        bfot_token_address: cfg.bfot_token_address.into(),
-        gfot_token_address: cfg.gfot_token_address.into(),
-        fot_amount: cfg.fot_amount,
-        gfot_amount: cfg.gfot_amount,
-        daily_fot_amount: cfg.daily_fot_amount,
-        apy_prefix: cfg.apy_prefix,
-        delta_time: cfg.delta_time,
-        reward_interval: cfg.reward_interval,
-        lock_days: cfg.lock_days
-    })
+/// Part of `owed` a claim is paid now. In underfunded mode every claim gets the same share
+/// of what the pool can cover, otherwise it is paid in full or not at all.
+fn claimable(cfg: &Config, state: &RewardState, owed: Uint128) -> Uint128 {
+    if cfg.fot_amount >= owed && (!cfg.underfunded_payouts || cfg.fot_amount >= state.liabilities) {
+        owed
+    } else if cfg.underfunded_payouts {
+        owed.multiply_ratio(cfg.fot_amount, state.liabilities.max(owed))
+    } else {
+        Uint128::zero()
+    }
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+pub fn try_claim_reward(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    staker: Option<String>,
+    recipient: Option<String>
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let staker = resolve_staker(&deps, &env, &info, staker)?;
+    let recipient = maybe_addr(deps.api, recipient)?.unwrap_or_else(|| staker.clone());
+    update_reward(deps.storage, env.clone(), pool_id, staker.clone())?;
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
 
-fn query_staker(deps: Deps, address: Addr) -> StdResult<StakerResponse> {
+    let row = stakers().may_load(deps.storage, (pool_id, staker.clone()))?;
+    let (amount, reward, last_time, sfot_reward) = row.unwrap_or_default();
+    let iou = IOUS.may_load(deps.storage, (pool_id, staker.clone()))?.unwrap_or_default();
+    let owed = reward + iou;
     
-    let exists = STAKERS.may_load(deps.storage, address.clone())?;
-    let (mut amount, mut reward, mut last_time, mut sfot_reward) = (Uint128::zero(), Uint128::zero(), 0u64, Uint128::zero());
-    if exists.is_some() {
-        (amount, reward, last_time, sfot_reward) = exists.unwrap();
-    } 
-    Ok(StakerResponse {
-        address,
-        amount,
-        reward,
-        last_time,
-        sfot_reward
-    })
+    if owed == Uint128::zero() {
+        return Err(ContractError::NoReward {});
+    }
+
+    let state = REWARD_STATE.load(deps.storage, pool_id)?;
+    let paid = claimable(&cfg, &state, owed);
+    if paid.is_zero() {
+        return Err(ContractError::NotEnoughFOT {});
+    }
+    
+    cfg.fot_amount -= paid;
+    let net = take_fee(deps.storage, pool_id, &mut cfg, paid)?;
+    let msgs = pay_reward(deps.storage, &env, pool_id, &mut cfg, staker.clone(), recipient.clone(), net)?;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+    update_reward_state(deps.storage, pool_id, |state| state.liabilities = state.liabilities.saturating_sub(paid))?;
+    
+    // a staker force exited earlier may only hold an IOU
+    if row.is_some() {
+        stakers().save(deps.storage, (pool_id, staker.clone()), &(amount, Uint128::zero(), last_time, sfot_reward))?;
+    }
+    let iou = owed - paid;
+    set_iou(deps.storage, pool_id, staker.clone(), iou)?;
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attributes(vec![
+            attr("action", "claim_reward"),
+            attr("address", staker),
+            attr("recipient", recipient),
+            attr("fot_amount", net),
+            attr("fee_amount", paid - net),
+            attr("iou_amount", iou),
+            attr("vesting", cfg.vesting.is_some().to_string()),
+        ]))
+}
+
+/// Takes the protocol fee off a reward payout into fee_amount and returns the rest.
+/// The payout must already be out of fot_amount; the caller saves `cfg`.
+fn take_fee(storage: &mut dyn Storage, pool_id: u64, cfg: &mut Config, amount: Uint128) -> StdResult<Uint128> {
+    let fee = amount.multiply_ratio(cfg.fee_bps, BPS_DENOMINATOR);
+    if !fee.is_zero() {
+        cfg.fee_amount += fee;
+        update_stats(storage, pool_id, |stats| stats.fees_taken += fee)?;
+    }
+    Ok(amount - fee)
 }
 
+pub fn try_collect_fees(deps: DepsMut, info: MessageInfo, pool_id: u64) -> Result<Response, ContractError> {
 
-fn query_unstaking(deps: Deps, address: Addr) -> StdResult<Vec<(Uint128, u64)>> {
-    
-    let mut exists = UNSTAKING.may_load(deps.storage, address.clone())?;
-    let mut unstaking = vec![];
-    if exists.is_some() {
-        unstaking = exists.unwrap();
-    } 
-    Ok(unstaking)
+    check_enabled(&deps, &info, pool_id)?;
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    let treasury = cfg.treasury.clone().ok_or(ContractError::NoTreasury {})?;
+    let fee_amount = cfg.fee_amount;
+    if fee_amount.is_zero() {
+        return Err(ContractError::InvalidInput {});
+    }
+
+    cfg.fee_amount = Uint128::zero();
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+    update_stats(deps.storage, pool_id, |stats| stats.fees_collected += fee_amount)?;
+
+    let exec_transfer = transfer_msg(reward_balance(&cfg, fee_amount), treasury.clone())?;
+
+    Ok(Response::new()
+        .add_message(exec_transfer)
+        .add_attributes(vec![
+            attr("action", "collect_fees"),
+            attr("treasury", treasury),
+            attr("fee_amount", fee_amount),
+        ]))
 }
 
-fn map_staker(
-    item: StdResult<(Addr, (Uint128, Uint128, u64, Uint128))>,
-) -> StdResult<StakerInfo> {
-    item.map(|(address, (amount, reward, last_time, sfot_reward))| {
-        StakerInfo {
-            address,
-            amount,
-            reward,
-            last_time,
-            sfot_reward
+/// Pays out a reward claimed by `staker`. In vesting mode it vests for the staker, who picks
+/// the recipient when withdrawing it, otherwise it is sent to `recipient`.
+/// The payout must already be out of fot_amount; the caller saves `cfg`.
+fn pay_reward(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_id: u64,
+    cfg: &mut Config,
+    staker: Addr,
+    recipient: Addr,
+    amount: Uint128
+) -> StdResult<Vec<CosmosMsg>> {
+    if amount.is_zero() {
+        return Ok(vec![]);
+    }
+    match cfg.vesting.clone() {
+        Some(vesting) => {
+            add_vesting(storage, env, pool_id, staker, &vesting, amount)?;
+            cfg.vesting_amount += amount;
+            Ok(vec![])
+        },
+        None => {
+            update_stats(storage, pool_id, |stats| stats.fot_claimed += amount)?;
+            Ok(vec![transfer_msg(reward_balance(cfg, amount), recipient)?])
         }
-    })
+    }
 }
 
-fn query_list_stakers(
-    deps: Deps,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<StakerListResponse> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let addr = maybe_addr(deps.api, start_after)?;
-    let start = addr.map(|addr| Bound::exclusive(addr.as_ref()));
-
-    let stakers:StdResult<Vec<_>> = STAKERS
-        .range(deps.storage, start, None, Order::Ascending)
-        .take(limit)
-        .map(|item| map_staker(item))
-        .collect();
+/// Puts a claimed reward of `staker` into vesting; the caller adds it to vesting_amount
+fn add_vesting(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_id: u64,
+    staker: Addr,
+    vesting: &Vesting,
+    amount: Uint128
+) -> StdResult<()> {
+    let now = env.block.time.seconds();
+    let mut entries = VESTING.may_load(storage, (pool_id, staker.clone()))?.unwrap_or_default();
+    match entries.last_mut() {
+        // claims of the same block on the same terms share an entry
+        Some(last) if last.start_time == now && last.end_time == now + vesting.duration && last.cliff_time == now + vesting.cliff => {
+            last.amount += amount;
+        },
+        _ => entries.push(VestingEntry {
+            amount,
+            withdrawn: Uint128::zero(),
+            start_time: now,
+            cliff_time: now + vesting.cliff,
+            end_time: now + vesting.duration
+        })
+    }
+    VESTING.save(storage, (pool_id, staker), &entries)
+}
 
-    Ok(StakerListResponse { stakers: stakers? })
+/// Part of a vesting entry vested at `now`, withdrawn or not
+fn vested_amount(entry: &VestingEntry, now: u64) -> Uint128 {
+    if now < entry.cliff_time {
+        Uint128::zero()
+    } else if now >= entry.end_time {
+        entry.amount
+    } else {
+        entry.amount.multiply_ratio(now - entry.start_time, entry.end_time - entry.start_time)
+    }
 }
 
-pub fn query_apy(deps: Deps) -> StdResult<Uint128> {
-    let cfg = CONFIG.load(deps.storage)?;
-    let total_staked_gfot = cfg.gfot_amount;
-    if total_staked_gfot == Uint128::zero() {
-        return Ok(Uint128::zero());
+pub fn try_withdraw_vested(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    early: bool,
+    recipient: Option<String>
+) -> Result<Response, ContractError> {
+
+    // vested rewards stay withdrawable while the pool is disabled
+    let recipient = maybe_addr(deps.api, recipient)?.unwrap_or_else(|| info.sender.clone());
+    let now = env.block.time.seconds();
+    let entries = VESTING.may_load(deps.storage, (pool_id, info.sender.clone()))?.unwrap_or_default();
+
+    let mut released = Uint128::zero();
+    let mut forfeited = Uint128::zero();
+    let mut vesting = vec![];
+    for mut entry in entries {
+        let vested = vested_amount(&entry, now);
+        released += vested - entry.withdrawn;
+        if early {
+            forfeited += entry.amount - vested;
+        } else if vested < entry.amount {
+            entry.withdrawn = vested;
+            vesting.push(entry);
+        }
+    }
+    if released.is_zero() && forfeited.is_zero() {
+        return Err(ContractError::NoReward {});
     }
-    // For integer handling, return apy * MULTIPLE(10^10)
 
-    // gFot_minting_cost: This is calculated by 1 / (GFOT current supply / 10^10 + 10000)
-    let gfot_token_info: TokenInfoResponse =
-        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: cfg.gfot_token_address.clone().into(),
-            msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
-        }))?;
-    
-    let gfot_current_supply = Uint128::from(gfot_token_info.total_supply);
+    if vesting.is_empty() {
+        VESTING.remove(deps.storage, (pool_id, info.sender.clone()));
+    } else {
+        VESTING.save(deps.storage, (pool_id, info.sender.clone()), &vesting)?;
+    }
 
-    let gfot_rate = (gfot_current_supply.checked_div(Uint128::from(10_000_000_000u128)).unwrap())
-    .checked_add(Uint128::from(10000u128)).unwrap();
-    // let gfot_minting_cost = 1.0 / (gfot_rate.u128() as f64);
-    
-    
-    // bFot_receiving_ratio: This is calculated by 109 - (FOT current supply - 1) / 10^16
-    // let fot_token_info: TokenInfoResponse =
-    //     deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-    //         contract_addr: cfg.fot_token_address.clone().into(),
-    //         msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
-    //     }))?;
+    // forfeits are distributed at the reward index of now
+    update_global_reward(deps.storage, &env, pool_id)?;
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    cfg.vesting_amount -= released + forfeited;
+    let redistributed = take_fee(deps.storage, pool_id, &mut cfg, forfeited)?;
+    distribute_reward(deps.storage, pool_id, &mut cfg, redistributed)?;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
 
-    // let fot_current_supply = Uint128::from(fot_token_info.total_supply);
-    // let fot_rate = (fot_current_supply - Uint128::from(1u128)).checked_div(Uint128::from(10_000_000_000_000_000u128)).unwrap();
-    // let bfot_receiving_ratio = Uint128::from(109u128) - fot_rate;
+    let mut msgs = vec![];
+    if !released.is_zero() {
+        update_stats(deps.storage, pool_id, |stats| stats.fot_claimed += released)?;
+        msgs.push(transfer_msg(reward_balance(&cfg, released), recipient.clone())?);
+    }
 
-    
-    Ok(cfg.apy_prefix.checked_mul(Uint128::from(MULTIPLE)).unwrap().checked_mul(Uint128::from(MULTIPLE)).unwrap().checked_div(gfot_rate).unwrap().checked_div(total_staked_gfot).unwrap())
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attributes(vec![
+            attr("action", "withdraw_vested"),
+            attr("address", info.sender),
+            attr("recipient", recipient),
+            attr("fot_amount", released),
+            attr("forfeited_amount", forfeited),
+            attr("fee_amount", forfeited - redistributed),
+        ]))
+}
 
-    // Ok(cfg.apy_prefix.checked_mul(Uint128::from(MULTIPLE)).unwrap().checked_mul(bfot_receiving_ratio).unwrap().checked_div(gfot_rate).unwrap().checked_div(total_staked_gfot).unwrap())
-    // Ok(Uint128::from(365000000u128).checked_mul(bfot_receiving_ratio).unwrap().checked_div(gfot_rate).unwrap())
+/// Settles `address` at the multiplier of its boost lock, then changes the lock
+/// and moves it to the multiplier of the new lock
+fn update_boost_lock<F>(storage: &mut dyn Storage, env: &Env, pool_id: u64, address: Addr, action: F) -> Result<(), ContractError>
+where F: FnOnce(&mut BoostLock) -> Result<(), ContractError>
+{
+    update_reward(storage, env.clone(), pool_id, address.clone())?;
+    let mut lock = BOOST_LOCKS.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    action(&mut lock)?;
+    if lock.bfot.is_zero() && lock.nfts.is_empty() {
+        BOOST_LOCKS.remove(storage, (pool_id, address.clone()));
+    } else {
+        BOOST_LOCKS.save(storage, (pool_id, address.clone()), &lock)?;
+    }
+    let amount = stakers().may_load(storage, (pool_id, address.clone()))?.unwrap_or_default().0;
+    if let Some(age) = STAKE_AGES.may_load(storage, (pool_id, address.clone()))? {
+        set_stake_age(storage, pool_id, address, amount, age.multiplier, age.since, env.block.time.seconds())?;
+    }
+    Ok(())
 }
 
+fn lock_bfot(
+    deps: DepsMut,
+    env: Env,
+    pool_id: u64,
+    cfg: Config,
+    user_addr: Addr,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+    let boost = cfg.boost.as_ref().ok_or(ContractError::NoBoost {})?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidInput {});
+    }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    let version = get_contract_version(deps.storage)?;
-    if version.contract != CONTRACT_NAME {
-        return Err(ContractError::CannotMigrate {
-            previous_contract: version.contract,
-        });
+    update_boost_lock(deps.storage, &env, pool_id, user_addr.clone(), |lock| {
+        lock.bfot += amount;
+        check_boost_cap(boost, lock)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "lock_bfot"),
+            attr("address", user_addr),
+            attr("amount", amount),
+        ]))
+}
+
+pub fn try_receive_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw721ReceiveMsg
+) -> Result<Response, ContractError> {
+    let pool_id = match from_json::<ReceiveMsg>(&wrapper.msg).ok() {
+        Some(ReceiveMsg::Boost { pool_id }) => pool_id,
+        Some(ReceiveMsg::UnstakePosition { pool_id }) => return unstake_position(deps, env, info, pool_id, wrapper),
+        Some(_) => return Err(ContractError::InvalidInput {}),
+        None => 0
+    };
+    check_enabled(&deps, &info, pool_id)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let boost = cfg.boost.as_ref().ok_or(ContractError::NoBoost {})?;
+    if !boost.collections.iter().any(|collection| collection.address == info.sender) {
+        return Err(ContractError::UnacceptableToken {});
     }
-    Ok(Response::default())
+
+    let user_addr = deps.api.addr_validate(&wrapper.sender)?;
+    update_boost_lock(deps.storage, &env, pool_id, user_addr.clone(), |lock| {
+        lock.nfts.push(LockedNft {
+            collection: info.sender.clone(),
+            token_id: wrapper.token_id.clone()
+        });
+        check_boost_cap(boost, lock)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "lock_nft"),
+            attr("address", user_addr),
+            attr("collection", info.sender),
+            attr("token_id", wrapper.token_id),
+        ]))
+}
+
+pub fn try_unlock_bfot(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    update_boost_lock(deps.storage, &env, pool_id, info.sender.clone(), |lock| {
+        if amount.is_zero() || lock.bfot < amount {
+            return Err(ContractError::NotLocked {});
+        }
+        lock.bfot -= amount;
+        Ok(())
+    })?;
+
+    let exec_transfer = transfer_msg(Balance::Cw20(Cw20CoinVerified {
+        address: cfg.bfot_token_address,
+        amount
+    }), info.sender.clone())?;
+
+    Ok(Response::new()
+        .add_message(exec_transfer)
+        .add_attributes(vec![
+            attr("action", "unlock_bfot"),
+            attr("address", info.sender),
+            attr("amount", amount),
+        ]))
 }
 
+pub fn try_unlock_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    collection: String,
+    token_id: String
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let collection = deps.api.addr_validate(&collection)?;
+    update_boost_lock(deps.storage, &env, pool_id, info.sender.clone(), |lock| {
+        let index = lock.nfts.iter()
+            .position(|nft| nft.collection == collection && nft.token_id == token_id)
+            .ok_or(ContractError::NotLocked {})?;
+        lock.nfts.remove(index);
+        Ok(())
+    })?;
+
+    let exec_cw721_transfer = WasmMsg::Execute {
+        contract_addr: collection.to_string(),
+        msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
+            recipient: info.sender.to_string(),
+            token_id: token_id.clone()
+        })?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(exec_cw721_transfer)
+        .add_attributes(vec![
+            attr("action", "unlock_nft"),
+            attr("address", info.sender),
+            attr("collection", collection),
+            attr("token_id", token_id),
+        ]))
+}
+
+/// Returns `amount` to the reward pool credited to the current stakers pro rata.
+/// Run update_global_reward first; the caller saves `cfg`.
+fn distribute_reward(storage: &mut dyn Storage, pool_id: u64, cfg: &mut Config, amount: Uint128) -> StdResult<()> {
+    cfg.fot_amount += amount;
+    let mut state = REWARD_STATE.load(storage, pool_id)?;
+    let weight = reward_weight(cfg, &state);
+    if amount.is_zero() || weight.is_zero() {
+        return Ok(());
+    }
+    state.reward_per_token += Decimal256::from_ratio(amount, weight);
+    state.liabilities += amount.multiply_ratio(state.total_weight + state.bonus_weight, weight);
+    REWARD_STATE.save(storage, pool_id, &state)
+}
+
+pub fn try_create_unstake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    unstake_amount: Uint128,
+    staker: Option<String>
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let staker = resolve_staker(&deps, &env, &info, staker)?;
+    update_reward(deps.storage, env.clone(), pool_id, staker.clone())?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let (amount, reward, last_time, sfot_reward) = stakers().may_load(deps.storage, (pool_id, staker.clone()))?.unwrap_or_default();
+    
+    if amount == Uint128::zero() {
+        return Err(ContractError::NoStaked {});
+    }
+    if cfg.staked_amount < amount {
+        return Err(ContractError::NotEnoughgFOT {});
+    }
+
+    if amount < unstake_amount {
+        return Err(ContractError::NotEnoughgFOT {});
+    }
+
+    let mut unstaking = UNSTAKING.may_load(deps.storage, (pool_id, staker.clone()))?.unwrap_or_default();
+
+    unstaking.push((unstake_amount, env.block.time.seconds() + cfg.lock_days * 86400u64));
+    UNSTAKING.save(deps.storage, (pool_id, staker.clone()), &unstaking)?;
+    update_stats(deps.storage, pool_id, |stats| stats.unbonding_count += 1)?;
+
+    // unstaking takes the loyalty of the stake age away
+    if let Some(loyalty) = &cfg.loyalty {
+        let now = env.block.time.seconds();
+        let age = STAKE_AGES.load(deps.storage, (pool_id, staker.clone()))?;
+        let reset = Uint128::from(now.saturating_sub(age.since)) * loyalty.unstake_reset;
+        let since = age.since + u64::try_from(reset.u128()).map_err(|_| StdError::generic_err("Stake age overflow"))?;
+        set_stake_age(deps.storage, pool_id, staker.clone(), amount, age.multiplier, since.min(now), now)?;
+    }
+
+    let diff = save_staker(deps.storage, &env, pool_id, staker.clone(), &(amount - unstake_amount, reward, last_time, sfot_reward))?;
+
+    Ok(Response::new()
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, diff.into_iter().collect())?)
+        .add_attributes(vec![
+            attr("action", "create_unstake"),
+            attr("address", staker),
+            attr("stake_amount", amount),
+        ]))
+}
+
+pub fn try_fetch_unstake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    index: u64,
+    staker: Option<String>,
+    recipient: Option<String>
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let staker = resolve_staker(&deps, &env, &info, staker)?;
+    let recipient = maybe_addr(deps.api, recipient)?.unwrap_or_else(|| staker.clone());
+    update_reward(deps.storage, env.clone(), pool_id, staker.clone())?;
+
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    
+    let mut list = UNSTAKING.may_load(deps.storage, (pool_id, staker.clone()))?
+        .ok_or(ContractError::NotCreatedUnstaking {})?;
+    
+    if (list.len() as u64) <= index {
+        return Err(ContractError::NotCreatedUnstaking {});
+    }
+    let (amount, timestamp) = list[index as usize];
+
+    if cfg.staked_amount < amount {
+        return Err(ContractError::NotEnoughgFOT {  });
+    }
+    if timestamp > env.block.time.seconds() {
+        return Err(ContractError::StillLocked {});
+    }
+    cfg.staked_amount -= amount;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+    
+    list.remove(index as usize);
+    UNSTAKING.save(deps.storage, (pool_id, staker.clone()), &list)?;
+    update_stats(deps.storage, pool_id, |stats| stats.unbonding_count = stats.unbonding_count.saturating_sub(1))?;
+
+    let exec_transfer = transfer_msg(stake_balance(&cfg, amount), recipient.clone())?;
+    
+    Ok(Response::new()
+        .add_message(exec_transfer)
+        .add_attributes(vec![
+            attr("action", "fetch_unstake"),
+            attr("address", staker),
+            attr("recipient", recipient),
+            attr("stake_amount", amount),
+        ]))
+}
+
+pub fn try_transfer_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    recipient: String,
+    transfer_amount: Uint128,
+    include_reward: bool
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    if !cfg.transfer_enabled {
+        return Err(ContractError::TransferDisabled {});
+    }
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    if recipient == info.sender || transfer_amount == Uint128::zero() {
+        return Err(ContractError::InvalidInput {});
+    }
+
+    // settle both sides before the staked amounts change
+    update_reward(deps.storage, env.clone(), pool_id, info.sender.clone())?;
+    update_reward(deps.storage, env.clone(), pool_id, recipient.clone())?;
+
+    let (amount, mut reward, last_time, sfot_reward) = stakers().may_load(deps.storage, (pool_id, info.sender.clone()))?.unwrap_or_default();
+    if amount < transfer_amount {
+        return Err(ContractError::NotEnoughgFOT {});
+    }
+    let (rcpt_amount, mut rcpt_reward, rcpt_last_time, rcpt_sfot_reward) = stakers().may_load(deps.storage, (pool_id, recipient.clone()))?.unwrap_or_default();
+
+    let mut moved_reward = Uint128::zero();
+    if include_reward {
+        moved_reward = reward;
+        rcpt_reward += reward;
+        reward = Uint128::zero();
+    }
+
+    let diffs = vec![
+        save_staker(deps.storage, &env, pool_id, info.sender.clone(), &(amount - transfer_amount, reward, last_time, sfot_reward))?,
+        save_staker(deps.storage, &env, pool_id, recipient.clone(), &(rcpt_amount + transfer_amount, rcpt_reward, rcpt_last_time, rcpt_sfot_reward))?,
+    ];
+
+    Ok(Response::new()
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, diffs.into_iter().flatten().collect())?)
+        .add_attributes(vec![
+            attr("action", "transfer_stake"),
+            attr("address", info.sender),
+            attr("recipient", recipient),
+            attr("stake_amount", transfer_amount),
+            attr("fot_amount", moved_reward),
+        ]))
+}
+
+pub fn execute_set_transfer_enabled(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    enabled: bool
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.transfer_enabled = enabled;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_transfer_enabled"),
+            attr("enabled", enabled.to_string()),
+        ]))
+}
+
+pub fn execute_set_underfunded_payouts(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    enabled: bool
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.underfunded_payouts = enabled;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_underfunded_payouts"),
+            attr("enabled", enabled.to_string()),
+        ]))
+}
+
+pub fn execute_set_position_nft(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    address: Option<String>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let position_nft = maybe_addr(deps.api, address)?;
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.position_nft = position_nft.clone();
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_position_nft"),
+            attr("position_nft", position_nft.map_or(String::new(), |a| a.into())),
+        ]))
+}
+
+pub fn execute_set_dao(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    dao: Option<String>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let dao = maybe_addr(deps.api, dao)?;
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.dao = dao.clone();
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_dao"),
+            attr("dao", dao.map_or(String::new(), |a| a.into())),
+        ]))
+}
+
+/// Validates the stake token, a cw20 address or a native denom
+fn validate_stake_token(api: &dyn Api, stake_token: AssetInfo) -> StdResult<AssetInfo> {
+    match stake_token {
+        AssetInfo::Token { contract_addr } => Ok(AssetInfo::Token {
+            contract_addr: api.addr_validate(&contract_addr)?.into()
+        }),
+        AssetInfo::NativeToken { denom } if denom.is_empty() => Err(StdError::generic_err("Empty stake denom")),
+        native => Ok(native)
+    }
+}
+
+/// `amount` of the staked token of the pool
+fn stake_balance(cfg: &Config, amount: Uint128) -> Balance {
+    match &cfg.stake_token {
+        AssetInfo::Token { contract_addr } => Balance::Cw20(Cw20CoinVerified {
+            address: Addr::unchecked(contract_addr),
+            amount
+        }),
+        AssetInfo::NativeToken { denom } => Balance::from(coins(amount.u128(), denom))
+    }
+}
+
+/// `amount` of the reward token of the pool, reward_denom if set, FOT otherwise
+fn reward_balance(cfg: &Config, amount: Uint128) -> Balance {
+    match &cfg.reward_denom {
+        Some(denom) => Balance::from(coins(amount.u128(), denom)),
+        None => Balance::Cw20(Cw20CoinVerified {
+            address: cfg.fot_token_address.clone(),
+            amount
+        })
+    }
+}
+
+/// Sends `balance` to `recipient`, native coins with a bank send
+fn transfer_msg(balance: Balance, recipient: impl Into<String>) -> StdResult<CosmosMsg> {
+    Ok(match balance {
+        Balance::Native(native) => BankMsg::Send {
+            to_address: recipient.into(),
+            amount: native.into_vec()
+        }.into(),
+        Balance::Cw20(coin) => WasmMsg::Execute {
+            contract_addr: coin.address.into(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.into(),
+                amount: coin.amount,
+            })?,
+            funds: vec![],
+        }.into()
+    })
+}
+
+fn validate_stake_decimals(decimals: u8) -> StdResult<u8> {
+    pow10(decimals)?;
+    Ok(decimals)
+}
+
+fn validate_price_source(api: &dyn Api, price_source: PriceSource) -> StdResult<PriceSource> {
+    Ok(match price_source {
+        PriceSource::PairPool { contract_addr, decimals } => PriceSource::PairPool {
+            contract_addr: api.addr_validate(contract_addr.as_str())?,
+            decimals
+        },
+        PriceSource::PairSimulation { contract_addr, decimals } => PriceSource::PairSimulation {
+            contract_addr: api.addr_validate(contract_addr.as_str())?,
+            decimals
+        },
+        price_source => price_source
+    })
+}
+
+pub fn execute_set_price_source(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    price_source: PriceSource
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let price_source = validate_price_source(deps.api, price_source)?;
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.price_source = price_source;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new().add_attribute("action", "set_price_source"))
+}
+
+pub fn execute_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    addr: String
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let addr = deps.api.addr_validate(&addr)?;
+    let mut hooks = HOOKS.may_load(deps.storage, pool_id)?.unwrap_or_default();
+    if hooks.contains(&addr) {
+        return Err(ContractError::HookAlreadyRegistered {});
+    }
+    hooks.push(addr.clone());
+    HOOKS.save(deps.storage, pool_id, &hooks)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "add_hook"),
+            attr("hook", addr),
+        ]))
+}
+
+pub fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    addr: String
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let addr = deps.api.addr_validate(&addr)?;
+    let mut hooks = HOOKS.may_load(deps.storage, pool_id)?.unwrap_or_default();
+    if !hooks.contains(&addr) {
+        return Err(ContractError::HookNotRegistered {});
+    }
+    hooks.retain(|hook| hook != addr);
+    HOOKS.save(deps.storage, pool_id, &hooks)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "remove_hook"),
+            attr("hook", addr),
+        ]))
+}
+
+pub fn try_claim_position_reward(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    token_id: String,
+    recipient: Option<String>
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    let nft = cfg.position_nft.clone().ok_or(ContractError::NoPositionNft {})?;
+    let id = parse_token_id(&token_id)?;
+
+    let owner = query_position_owner(deps.as_ref(), &nft, &token_id)?;
+    let owner = resolve_staker(&deps, &env, &info, Some(owner.into()))?;
+    let recipient = maybe_addr(deps.api, recipient)?.unwrap_or_else(|| owner.clone());
+
+    if positions().load(deps.storage, id)?.pool_id != pool_id {
+        return Err(ContractError::InvalidInput {});
+    }
+    let mut position = update_position_reward(deps.storage, &env, id)?;
+    let diffs = move_position_stake(deps.storage, env.block.height, &mut position, Some(owner.clone()))?;
+    // without a reward the claim only moves the stake to the current owner
+    let reward = position.reward;
+    let state = REWARD_STATE.load(deps.storage, pool_id)?;
+    let paid = claimable(&cfg, &state, reward);
+    if paid.is_zero() && !reward.is_zero() {
+        return Err(ContractError::NotEnoughFOT {});
+    }
+
+    cfg.fot_amount -= paid;
+    let net = take_fee(deps.storage, pool_id, &mut cfg, paid)?;
+    let msgs = pay_reward(deps.storage, &env, pool_id, &mut cfg, owner.clone(), recipient.clone(), net)?;
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+    update_reward_state(deps.storage, pool_id, |state| state.liabilities = state.liabilities.saturating_sub(paid))?;
+
+    // the unpaid part is owed to the owner
+    position.reward = Uint128::zero();
+    positions().save(deps.storage, id, &position)?;
+    let iou = reward - paid;
+    if !iou.is_zero() {
+        let prev = IOUS.may_load(deps.storage, (pool_id, owner.clone()))?.unwrap_or_default();
+        set_iou(deps.storage, pool_id, owner.clone(), prev + iou)?;
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, diffs)?)
+        .add_attributes(vec![
+            attr("action", "claim_position_reward"),
+            attr("address", owner),
+            attr("token_id", token_id),
+            attr("recipient", recipient),
+            attr("fot_amount", net),
+            attr("fee_amount", paid - net),
+            attr("iou_amount", iou),
+            attr("vesting", cfg.vesting.is_some().to_string()),
+        ]))
+}
+
+/// Unstakes a position NFT sent to the contract, which now owns it and can burn it
+fn unstake_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    wrapper: Cw721ReceiveMsg
+) -> Result<Response, ContractError> {
+
+    check_enabled(&deps, &info, pool_id)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let nft = cfg.position_nft.clone().ok_or(ContractError::NoPositionNft {})?;
+    if info.sender != nft {
+        return Err(ContractError::UnacceptableToken {});
+    }
+    let token_id = wrapper.token_id;
+    let id = parse_token_id(&token_id)?;
+    let owner = deps.api.addr_validate(&wrapper.sender)?;
+
+    if positions().load(deps.storage, id)?.pool_id != pool_id {
+        return Err(ContractError::InvalidInput {});
+    }
+    let mut position = update_position_reward(deps.storage, &env, id)?;
+    let diffs = move_position_stake(deps.storage, env.block.height, &mut position, None)?;
+    positions().remove(deps.storage, id)?;
+    update_reward_state(deps.storage, pool_id, |state| state.total_weight = state.total_weight.saturating_sub(position.amount))?;
+
+    // pending position reward becomes claimable through ClaimReward
+    update_reward(deps.storage, env.clone(), pool_id, owner.clone())?;
+    let (amount, reward, last_time, sfot_reward) = stakers().may_load(deps.storage, (pool_id, owner.clone()))?.unwrap_or_default();
+    stakers().save(deps.storage, (pool_id, owner.clone()), &(amount, reward + position.reward, last_time, sfot_reward))?;
+
+    let mut unstaking = UNSTAKING.may_load(deps.storage, (pool_id, owner.clone()))?.unwrap_or_default();
+    unstaking.push((position.amount, env.block.time.seconds() + position.lock_days * 86400u64));
+    UNSTAKING.save(deps.storage, (pool_id, owner.clone()), &unstaking)?;
+    update_stats(deps.storage, pool_id, |stats| stats.unbonding_count += 1)?;
+
+    let exec_cw721_burn = WasmMsg::Execute {
+        contract_addr: nft.into(),
+        msg: to_json_binary(&Cw721ExecuteMsg::Burn {
+            token_id: token_id.clone()
+        })?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(exec_cw721_burn)
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, diffs)?)
+        .add_attributes(vec![
+            attr("action", "unstake_position"),
+            attr("address", owner),
+            attr("token_id", token_id),
+            attr("stake_amount", position.amount),
+            attr("fot_amount", position.reward),
+        ]))
+}
+
+pub fn execute_approve_operator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+    expires: Option<Expiration>
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    if operator == info.sender {
+        return Err(ContractError::InvalidInput {});
+    }
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+
+    OPERATORS.save(deps.storage, (info.sender.clone(), operator.clone()), &expires)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "approve_operator"),
+            attr("address", info.sender),
+            attr("operator", operator),
+            attr("expires", expires.to_string()),
+        ]))
+}
+
+pub fn execute_revoke_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: String
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    OPERATORS.remove(deps.storage, (info.sender.clone(), operator.clone()));
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "revoke_operator"),
+            attr("address", info.sender),
+            attr("operator", operator),
+        ]))
+}
+
+pub fn check_owner(
+    deps: &DepsMut,
+    info: &MessageInfo,
+    pool_id: u64
+) -> Result<Response, ContractError> {
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let owner = cfg.owner.ok_or(ContractError::Unauthorized {})?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {})
+    }
+    Ok(Response::new().add_attribute("action", "check_owner"))
+}
+
+pub fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    new_owner: Option<String>,
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+    
+    //test code for checking if check_owner works well
+    // return Err(ContractError::InvalidInput {});
+    // if owner some validated to addr, otherwise set to none
+    let mut tmp_owner = None;
+    if let Some(addr) = new_owner {
+        tmp_owner = Some(deps.api.addr_validate(&addr)?)
+    }
+
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.owner = tmp_owner;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new().add_attribute("action", "update_config"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_update_constants(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    daily_fot_amount: Uint128,
+    apy_prefix: Uint128,
+    reward_interval: u64,
+    delta_time: u64,
+    lock_days: u64,
+    enabled: bool
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+    
+    //test code for checking if check_owner works well
+    // return Err(ContractError::InvalidInput {});
+    // if owner some validated to addr, otherwise set to none
+
+    // accrue at the old rate before it changes
+    update_global_reward(deps.storage, &env, pool_id)?;
+    
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.daily_fot_amount = daily_fot_amount;
+        exists.apy_prefix = apy_prefix;
+        exists.reward_interval = reward_interval;
+        exists.lock_days = lock_days;
+        exists.delta_time = delta_time;
+        exists.enabled = enabled;
+        if let Some(schedule) = exists.emission.clone() {
+            validate_emission_schedule(schedule, reward_interval)?;
+        }
+        Ok(exists)
+    })?;
+
+    Ok(Response::new().add_attribute("action", "update_constants"))
+}
+
+pub fn execute_add_stakers(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    new_stakers: Vec<StakerInfo>,
+    mode: ImportMode,
+    batch_id: String
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    if cfg.import_finalized {
+        return Err(ContractError::ImportFinalized {});
+    }
+    if IMPORT_BATCHES.has(deps.storage, (pool_id, &batch_id)) {
+        return Err(ContractError::BatchAlreadyImported { batch_id });
+    }
+    IMPORT_BATCHES.save(deps.storage, (pool_id, &batch_id), &env.block.height)?;
+
+    // validate the whole batch before anything is written
+    let mut seen = HashSet::new();
+    for staker in &new_stakers {
+        deps.api.addr_validate(staker.address.as_str())?;
+        if !seen.insert(staker.address.clone()) {
+            return Err(ContractError::DuplicateStaker { address: staker.address.to_string() });
+        }
+    }
+
+    let mut diffs = vec![];
+    for staker in &new_stakers {
+        // settle the existing entry so the imported reward replaces it in the liabilities
+        update_reward(deps.storage, env.clone(), pool_id, staker.address.clone())?;
+        let (prev_amount, prev_reward, prev_last_time, prev_sfot_reward) = stakers().may_load(deps.storage, (pool_id, staker.address.clone()))?.unwrap_or_default();
+        let data = match mode {
+            ImportMode::Add => (prev_amount + staker.amount, prev_reward + staker.reward, prev_last_time, prev_sfot_reward + staker.sfot_reward),
+            ImportMode::Overwrite => (staker.amount, staker.reward, staker.last_time, staker.sfot_reward)
+        };
+
+        update_reward_state(deps.storage, pool_id, |state| state.liabilities = (state.liabilities + data.1).saturating_sub(prev_reward))?;
+        // stakers imported before staked_amount was kept in line may not be counted in it
+        cfg.staked_amount = (cfg.staked_amount + data.0).saturating_sub(prev_amount);
+        diffs.extend(save_staker(deps.storage, &env, pool_id, staker.address.clone(), &data)?);
+    }
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+    
+    Ok(Response::new()
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, diffs)?)
+        .add_attributes(vec![
+            attr("action", "add_stakers"),
+            attr("mode", format!("{:?}", mode).to_lowercase()),
+            attr("batch_id", batch_id),
+            attr("count", new_stakers.len().to_string()),
+            attr("staked_amount", cfg.staked_amount),
+        ]))
+}
+
+fn validate_term(term: Term) -> StdResult<Term> {
+    if term.days == 0 {
+        return Err(StdError::generic_err("Term must last at least a day"));
+    }
+    Ok(term)
+}
+
+pub fn execute_set_term(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    term: Option<Term>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let term = term.map(validate_term).transpose()?;
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.term = term.clone();
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_term"),
+            attr("days", term.as_ref().map(|t| t.days.to_string()).unwrap_or_default()),
+            attr("rate", term.map(|t| t.rate.to_string()).unwrap_or_default()),
+        ]))
+}
+
+fn validate_vesting(vesting: Vesting) -> StdResult<Vesting> {
+    if vesting.duration == 0 || vesting.cliff > vesting.duration {
+        return Err(StdError::generic_err("Vesting needs a duration and a cliff within it"));
+    }
+    Ok(vesting)
+}
+
+pub fn execute_set_vesting(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    vesting: Option<Vesting>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let vesting = vesting.map(validate_vesting).transpose()?;
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.vesting = vesting.clone();
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_vesting"),
+            attr("duration", vesting.as_ref().map(|v| v.duration.to_string()).unwrap_or_default()),
+            attr("cliff", vesting.map(|v| v.cliff.to_string()).unwrap_or_default()),
+        ]))
+}
+
+fn validate_loyalty(loyalty: Loyalty) -> StdResult<Loyalty> {
+    if loyalty.cap < Decimal::one() || loyalty.unstake_reset > Decimal::one() {
+        return Err(StdError::generic_err("Loyalty cap must be at least one and the unstake reset at most one"));
+    }
+    if let LoyaltyCurve::Steps { steps } = &loyalty.curve {
+        if steps.windows(2).any(|pair| pair[0].days >= pair[1].days) {
+            return Err(StdError::generic_err("Loyalty step days must ascend"));
+        }
+    }
+    Ok(loyalty)
+}
+
+pub fn execute_set_loyalty(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    loyalty: Option<Loyalty>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let loyalty = loyalty.map(validate_loyalty).transpose()?;
+    let cap = loyalty.as_ref().map(|l| l.cap.to_string()).unwrap_or_default();
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.loyalty = loyalty;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_loyalty"),
+            attr("cap", cap),
+        ]))
+}
+
+fn validate_boost(api: &dyn Api, boost: Boost) -> StdResult<Boost> {
+    if boost.bfot_step.is_zero() || boost.cap < Decimal::one() {
+        return Err(StdError::generic_err("Boost needs a bFOT step and a cap of at least one"));
+    }
+    let mut seen = HashSet::new();
+    for collection in &boost.collections {
+        api.addr_validate(collection.address.as_str())?;
+        if !seen.insert(&collection.address) {
+            return Err(StdError::generic_err(format!("Duplicate boost collection {}", collection.address)));
+        }
+    }
+    Ok(boost)
+}
+
+pub fn execute_set_boost(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    boost: Option<Boost>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let boost = boost.map(|boost| validate_boost(deps.api, boost)).transpose()?;
+    let cap = boost.as_ref().map(|b| b.cap.to_string()).unwrap_or_default();
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.boost = boost;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_boost"),
+            attr("cap", cap),
+        ]))
+}
+
+fn validate_fee_bps(fee_bps: u64) -> StdResult<u64> {
+    if fee_bps > BPS_DENOMINATOR {
+        return Err(StdError::generic_err("Fee cannot exceed 10000 basis points"));
+    }
+    Ok(fee_bps)
+}
+
+pub fn execute_set_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    fee_bps: u64,
+    treasury: Option<String>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let fee_bps = validate_fee_bps(fee_bps)?;
+    let treasury = maybe_addr(deps.api, treasury)?;
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.fee_bps = fee_bps;
+        exists.treasury = treasury.clone();
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_fee"),
+            attr("fee_bps", fee_bps.to_string()),
+            attr("treasury", treasury.map(|t| t.to_string()).unwrap_or_default()),
+        ]))
+}
+
+pub fn execute_set_emission_schedule(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    schedule: Option<EmissionSchedule>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+    update_global_reward(deps.storage, &env, pool_id)?;
+
+    let reward_interval = POOLS.load(deps.storage, pool_id)?.reward_interval;
+    let schedule = schedule.map(|schedule| validate_emission_schedule(schedule, reward_interval)).transpose()?;
+    let kind = match &schedule {
+        None => "daily_fot_amount",
+        Some(EmissionSchedule::Segments { .. }) => "segments",
+        Some(EmissionSchedule::Decay { .. }) => "decay"
+    };
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.emission = schedule;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "set_emission_schedule"),
+            attr("schedule", kind),
+        ]))
+}
+
+pub fn execute_finalize_import(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    POOLS.update(deps.storage, pool_id, |exists| -> StdResult<_> {
+        let mut exists = exists.ok_or_else(|| StdError::not_found("pool"))?;
+        exists.import_finalized = true;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new().add_attribute("action", "finalize_import"))
+}
+
+pub fn execute_add_sfot_rewards(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    rewards: Vec<SfotRewardInfo>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let mut credited = Uint128::zero();
+    for rec in rewards {
+        let (amount, reward, last_time, mut sfot_reward) = stakers().load(deps.storage, (pool_id, rec.address.clone()))?;
+
+        sfot_reward += rec.sfot_reward;
+        credited += rec.sfot_reward;
+
+        stakers().save(deps.storage, (pool_id, rec.address.clone()), &(amount, reward, last_time, sfot_reward))?;
+    }
+    
+    update_stats(deps.storage, pool_id, |stats| stats.sfot_credited += credited)?;
+    
+    Ok(Response::new().add_attribute("action", "add_sfot_rewards"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_register_sfot_stage(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    merkle_root: String,
+    sfot_token_address: String,
+    total_amount: Uint128,
+    start: Option<Scheduled>,
+    expiration: Option<Expiration>
+) -> Result<Response, ContractError> {
+    // authorize admin
+    check_admin(&deps, &info)?;
+
+    // check merkle root length
+    let mut root_buf: [u8; 32] = [0; 32];
+    hex::decode_to_slice(&merkle_root, &mut root_buf)?;
+
+    let expiration = expiration.unwrap_or_default();
+    if expiration.is_expired(&env.block) || total_amount.is_zero() {
+        return Err(ContractError::InvalidInput {});
+    }
+    let sfot_token_address = deps.api.addr_validate(&sfot_token_address)?;
+
+    // open stages of the same token are paid from the same balance
+    let mut needed = total_amount;
+    for item in SFOT_STAGES.range(deps.storage, None, None, Order::Ascending) {
+        let (_, other) = item?;
+        if other.sfot_token_address == sfot_token_address && !other.reclaimed {
+            needed += other.total_amount - other.claimed_amount;
+        }
+    }
+    let balance: cw20::BalanceResponse = deps.querier.query_wasm_smart(&sfot_token_address, &Cw20QueryMsg::Balance {
+        address: env.contract.address.to_string()
+    })?;
+    if balance.balance < needed {
+        return Err(ContractError::NotEnoughsFOT { needed });
+    }
+
+    let stage = LATEST_SFOT_STAGE.may_load(deps.storage)?.unwrap_or_default()
+        .checked_add(1)
+        .ok_or(ContractError::InvalidInput {})?;
+    LATEST_SFOT_STAGE.save(deps.storage, &stage)?;
+    SFOT_STAGES.save(deps.storage, stage, &SfotStage {
+        merkle_root: merkle_root.clone(),
+        sfot_token_address,
+        total_amount,
+        claimed_amount: Uint128::zero(),
+        start,
+        expiration,
+        reclaimed: false
+    })?;
+    update_sfot_totals(deps.storage, |totals| totals.registered += total_amount)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "register_sfot_stage"),
+            attr("stage", stage.to_string()),
+            attr("merkle_root", merkle_root),
+            attr("total_amount", total_amount),
+        ]))
+}
+
+fn update_sfot_totals<F: FnOnce(&mut SfotTotals)>(storage: &mut dyn Storage, action: F) -> StdResult<()> {
+    let mut totals = SFOT_TOTALS.may_load(storage)?.unwrap_or_default();
+    action(&mut totals);
+    SFOT_TOTALS.save(storage, &totals)
+}
+
+pub fn try_claim_sfot(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stage: u8,
+    amount: Uint128,
+    proof: Vec<String>
+) -> Result<Response, ContractError> {
+
+    let mut sfot_stage = SFOT_STAGES.load(deps.storage, stage)?;
+    if let Some(start) = sfot_stage.start {
+        if !start.is_triggered(&env.block) {
+            return Err(ContractError::StageNotBegun { stage, start });
+        }
+    }
+    if sfot_stage.expiration.is_expired(&env.block) {
+        return Err(ContractError::StageExpired { stage, expiration: sfot_stage.expiration });
+    }
+    if SFOT_CLAIMED.may_load(deps.storage, (stage, info.sender.clone()))?.unwrap_or(false) {
+        return Err(ContractError::Claimed {});
+    }
+
+    verify_merkle_proof(&sfot_stage.merkle_root, &format!("{}{}", info.sender, amount), proof)?;
+    if sfot_stage.claimed_amount + amount > sfot_stage.total_amount {
+        return Err(ContractError::StageExceeded { stage });
+    }
+
+    SFOT_CLAIMED.save(deps.storage, (stage, info.sender.clone()), &true)?;
+    sfot_stage.claimed_amount += amount;
+    SFOT_STAGES.save(deps.storage, stage, &sfot_stage)?;
+    update_sfot_totals(deps.storage, |totals| totals.claimed += amount)?;
+
+    let exec_cw20_transfer = WasmMsg::Execute {
+        contract_addr: sfot_stage.sfot_token_address.into(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: info.sender.clone().into(),
+            amount,
+        })?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(exec_cw20_transfer)
+        .add_attributes(vec![
+            attr("action", "claim_sfot"),
+            attr("stage", stage.to_string()),
+            attr("address", info.sender),
+            attr("sfot_amount", amount),
+        ]))
+}
+
+/// Checks the hex encoded `proof` links sha256(`leaf`) to the hex encoded `merkle_root`,
+/// hashing each pair in sorted order
+fn verify_merkle_proof(merkle_root: &str, leaf: &str, proof: Vec<String>) -> Result<(), ContractError> {
+    let hash: [u8; 32] = sha2::Sha256::digest(leaf.as_bytes()).into();
+
+    let hash = proof.into_iter().try_fold(hash, |hash, p| -> Result<[u8; 32], ContractError> {
+        let mut proof_buf = [0; 32];
+        hex::decode_to_slice(p, &mut proof_buf)?;
+        let mut hashes = [hash, proof_buf];
+        hashes.sort_unstable();
+        Ok(sha2::Sha256::digest(&hashes.concat()).into())
+    })?;
+
+    let mut root_buf: [u8; 32] = [0; 32];
+    hex::decode_to_slice(merkle_root, &mut root_buf)?;
+    if root_buf != hash {
+        return Err(ContractError::VerificationFailed {});
+    }
+    Ok(())
+}
+
+pub fn execute_reclaim_sfot(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stage: u8
+) -> Result<Response, ContractError> {
+    // authorize admin
+    check_admin(&deps, &info)?;
+
+    let mut sfot_stage = SFOT_STAGES.load(deps.storage, stage)?;
+    if !sfot_stage.expiration.is_expired(&env.block) {
+        return Err(ContractError::StageNotExpired { stage });
+    }
+    if sfot_stage.reclaimed {
+        return Err(ContractError::Claimed {});
+    }
+
+    let amount = sfot_stage.total_amount - sfot_stage.claimed_amount;
+    sfot_stage.reclaimed = true;
+    SFOT_STAGES.save(deps.storage, stage, &sfot_stage)?;
+    update_sfot_totals(deps.storage, |totals| totals.reclaimed += amount)?;
+
+    let mut msgs = vec![];
+    if !amount.is_zero() {
+        msgs.push(WasmMsg::Execute {
+            contract_addr: sfot_stage.sfot_token_address.into(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.clone().into(),
+                amount,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attributes(vec![
+            attr("action", "reclaim_sfot"),
+            attr("stage", stage.to_string()),
+            attr("address", info.sender),
+            attr("sfot_amount", amount),
+        ]))
+}
+
+pub fn execute_migrate_stakers(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, 0)?;
+
+    let mut migration = LEGACY_MIGRATION.may_load(deps.storage)?.ok_or(ContractError::InvalidInput {})?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // stakers first, then unstaking lists left without a staker, then positions
+    let mut addresses = LEGACY_STAKERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    if addresses.len() < limit {
+        let unstaking:StdResult<Vec<_>> = LEGACY_UNSTAKING
+            .keys(deps.storage, None, None, Order::Ascending)
+            .take(limit - addresses.len())
+            .collect();
+        addresses.extend(unstaking?);
+    }
+    for address in &addresses {
+        migrate_legacy_staker(deps.storage, &env, address)?;
+    }
+
+    let mut migrated = addresses.len();
+    if migrated < limit {
+        let ids:StdResult<Vec<_>> = positions()
+            .keys(deps.storage, migration.last_position.map(Bound::exclusive), None, Order::Ascending)
+            .take(limit - migrated)
+            .collect();
+        for id in ids? {
+            let mut position = positions().load(deps.storage, id)?;
+            migrate_legacy_position(deps.storage, &env, &mut position)?;
+            positions().save(deps.storage, id, &position)?;
+            migration.last_position = Some(id);
+            migrated += 1;
+        }
+    }
+
+    // a short batch means everything is moved
+    let done = migrated < limit;
+    if done {
+        LEGACY_MIGRATION.remove(deps.storage);
+        CONFIG.remove(deps.storage);
+    } else {
+        LEGACY_MIGRATION.save(deps.storage, &migration)?;
+    }
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "migrate_stakers"),
+            attr("migrated", migrated.to_string()),
+            attr("done", done.to_string()),
+        ]))
+}
+
+pub fn execute_force_exit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    address: String
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    let exit = force_exit_staker(deps.storage, &env, pool_id, address.clone())?;
+
+    Ok(Response::new()
+        .add_messages(exit.msgs)
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, exit.diffs)?)
+        .add_attributes(vec![
+            attr("action", "force_exit"),
+            attr("address", address),
+            attr("stake_amount", exit.stake_amount),
+            attr("fot_amount", exit.fot_amount),
+            attr("fee_amount", exit.fee_amount),
+        ]))
+}
+
+pub fn execute_purge_stakers(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    check_owner(&deps, &info, pool_id)?;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let start = addr.map(Bound::exclusive);
+    let addresses:StdResult<Vec<_>> = stakers()
+        .prefix(pool_id)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect();
+    let mut addresses = addresses?;
+    if addresses.len() < limit {
+        let holders = pool_holders(deps.storage, pool_id, &addresses, limit - addresses.len())?;
+        addresses.extend(holders);
+    }
+
+    let mut msgs = vec![];
+    let mut diffs = vec![];
+    let mut stake_amount = Uint128::zero();
+    let mut fot_amount = Uint128::zero();
+    let mut fee_amount = Uint128::zero();
+    for address in &addresses {
+        let exit = force_exit_staker(deps.storage, &env, pool_id, address.clone())?;
+        msgs.extend(exit.msgs);
+        diffs.extend(exit.diffs);
+        stake_amount += exit.stake_amount;
+        fot_amount += exit.fot_amount;
+        fee_amount += exit.fee_amount;
+    }
+
+    // a short page means the stakers and other holders are exhausted
+    let next_start_after = if addresses.len() < limit {
+        None
+    } else {
+        addresses.last().map(|addr| addr.to_string())
+    };
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_submessages(member_changed_hooks(deps.storage, pool_id, diffs)?)
+        .set_data(to_json_binary(&PurgeStakersResponse {
+            purged: addresses.len() as u32,
+            next_start_after: next_start_after.clone()
+        })?)
+        .add_attributes(vec![
+            attr("action", "purge_stakers"),
+            attr("purged", addresses.len().to_string()),
+            attr("next_start_after", next_start_after.unwrap_or_default()),
+            attr("stake_amount", stake_amount),
+            attr("fot_amount", fot_amount),
+            attr("fee_amount", fee_amount),
+        ]))
+}
+
+struct ForceExit {
+    msgs: Vec<CosmosMsg>,
+    diffs: Vec<MemberDiff>,
+    stake_amount: Uint128,
+    fot_amount: Uint128,
+    fee_amount: Uint128
+}
+
+/// Depositors, position owners, boost lockers and legacy stakers of the pool not in `exclude`,
+/// at most `limit`.
+/// Force exits remove what they hold, so the first ones left are the next to purge.
+fn pool_holders(
+    storage: &dyn Storage,
+    pool_id: u64,
+    exclude: &[Addr],
+    limit: usize
+) -> StdResult<Vec<Addr>> {
+    let mut holders: Vec<Addr> = vec![];
+    for item in DEPOSITS.range(storage, None, None, Order::Ascending) {
+        let ((owner, _), deposit) = item?;
+        if deposit.pool_id != pool_id || exclude.contains(&owner) || holders.last() == Some(&owner) {
+            continue;
+        }
+        if holders.len() == limit {
+            return Ok(holders);
+        }
+        holders.push(owner);
+    }
+
+    // pool 0 also holds the stakers not yet moved by MigrateStakers
+    let legacy = if pool_id == 0 {
+        LEGACY_STAKERS.keys(storage, None, None, Order::Ascending)
+            .chain(LEGACY_UNSTAKING.keys(storage, None, None, Order::Ascending))
+            .collect::<StdResult<Vec<_>>>()?
+    } else {
+        vec![]
+    };
+    let others = POSITION_STAKES.prefix(pool_id).keys(storage, None, None, Order::Ascending)
+        .chain(BOOST_LOCKS.prefix(pool_id).keys(storage, None, None, Order::Ascending))
+        .chain(legacy.into_iter().map(Ok));
+    for owner in others {
+        let owner = owner?;
+        if exclude.contains(&owner) || holders.contains(&owner) {
+            continue;
+        }
+        if holders.len() == limit {
+            break;
+        }
+        holders.push(owner);
+    }
+    Ok(holders)
+}
+
+/// Settles the positions counted for `address` and removes them. Returns their amount, reward
+/// and the member diffs of taking them off its staked balance. The position NFTs are left to
+/// their holders without a position behind them.
+fn exit_positions(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_id: u64,
+    address: &Addr
+) -> Result<(Uint128, Uint128, Vec<MemberDiff>), ContractError> {
+    let ids:StdResult<Vec<_>> = positions()
+        .idx
+        .owner
+        .prefix((pool_id, address.clone()))
+        .keys(storage, None, None, Order::Ascending)
+        .collect();
+
+    let mut amount = Uint128::zero();
+    let mut reward = Uint128::zero();
+    let mut diffs = vec![];
+    for id in ids? {
+        let mut position = update_position_reward(storage, env, id)?;
+        diffs.extend(move_position_stake(storage, env.block.height, &mut position, None)?);
+        positions().remove(storage, id)?;
+        amount += position.amount;
+        reward += position.reward;
+    }
+    update_reward_state(storage, pool_id, |state| state.total_weight = state.total_weight.saturating_sub(amount))?;
+    Ok((amount, reward, diffs))
+}
+
+/// Ends the deposits of `address` in the pool, returning their principal and the part of
+/// their reward accrued so far. The rest of the reserved reward goes back to the reward pool.
+fn exit_deposits(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_id: u64,
+    address: &Addr
+) -> Result<ForceExit, ContractError> {
+    let deposits: StdResult<Vec<_>> = DEPOSITS
+        .prefix(address.clone())
+        .range(storage, None, None, Order::Ascending)
+        .collect();
+    let mut cfg = POOLS.load(storage, pool_id)?;
+    let now = env.block.time.seconds();
+
+    let mut amount = Uint128::zero();
+    let mut reward = Uint128::zero();
+    for (id, deposit) in deposits? {
+        if deposit.pool_id != pool_id {
+            continue;
+        }
+        let accrued = match deposit.maturity.saturating_sub(deposit.start_time) {
+            0 => deposit.reward,
+            term => deposit.reward.multiply_ratio(now.min(deposit.maturity).saturating_sub(deposit.start_time), term)
+        };
+        DEPOSITS.remove(storage, (address.clone(), id));
+        cfg.reserved_amount -= deposit.reward;
+        cfg.deposited_amount -= deposit.amount;
+        cfg.fot_amount += deposit.reward - accrued;
+        amount += deposit.amount;
+        reward += accrued;
+    }
+    let paid = take_fee(storage, pool_id, &mut cfg, reward)?;
+    POOLS.save(storage, pool_id, &cfg)?;
+    update_stats(storage, pool_id, |stats| stats.fot_claimed += paid)?;
+
+    let mut msgs = vec![];
+    if !amount.is_zero() {
+        msgs.push(transfer_msg(stake_balance(&cfg, amount), address.clone())?);
+    }
+    if !paid.is_zero() {
+        msgs.push(transfer_msg(reward_balance(&cfg, paid), address.clone())?);
+    }
+
+    Ok(ForceExit {
+        msgs,
+        diffs: vec![],
+        stake_amount: amount,
+        fot_amount: paid,
+        fee_amount: reward - paid
+    })
+}
+
+/// Removes a staker, returning its staked and unbonding gFOT, its positions, deposits and boost
+/// lock and paying its reward and IOU as far as the pool covers them. What the pool cannot pay
+/// stays owed as an IOU.
+fn force_exit_staker(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_id: u64,
+    address: Addr
+) -> Result<ForceExit, ContractError> {
+    if pool_id == 0 {
+        migrate_legacy_staker(storage, env, &address)?;
+    }
+    let mut exit = exit_deposits(storage, env, pool_id, &address)?;
+    let unbonding = UNSTAKING.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    let lock = BOOST_LOCKS.may_load(storage, (pool_id, address.clone()))?;
+    let (position_amount, position_reward, mut diffs) = exit_positions(storage, env, pool_id, &address)?;
+    if stakers().may_load(storage, (pool_id, address.clone()))?.is_none() && unbonding.is_empty()
+        && lock.is_none() && position_amount.is_zero() {
+        if exit.msgs.is_empty() {
+            return Err(ContractError::NoStaked {});
+        }
+        return Ok(exit);
+    }
+
+    update_reward(storage, env.clone(), pool_id, address.clone())?;
+    let mut cfg = POOLS.load(storage, pool_id)?;
+    let (amount, reward, last_time, sfot_reward) = stakers().may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+    let iou = IOUS.may_load(storage, (pool_id, address.clone()))?.unwrap_or_default();
+
+    let owed = reward + position_reward + iou;
+    let paid = owed.min(cfg.fot_amount);
+    let stake_amount = amount + position_amount + unbonding.iter().map(|(amount, _)| *amount).sum::<Uint128>();
+
+    // take the reward off the row so removing it does not forfeit what is paid or still owed
+    stakers().save(storage, (pool_id, address.clone()), &(amount, Uint128::zero(), last_time, sfot_reward))?;
+    set_iou(storage, pool_id, address.clone(), Uint128::zero())?;
+    diffs.extend(remove_staker(storage, env.block.height, pool_id, address.clone())?);
+    set_iou(storage, pool_id, address.clone(), owed - paid)?;
+    UNSTAKING.remove(storage, (pool_id, address.clone()));
+
+    cfg.fot_amount -= paid;
+    cfg.staked_amount = cfg.staked_amount.saturating_sub(stake_amount);
+    let net = take_fee(storage, pool_id, &mut cfg, paid)?;
+    let rewards = pay_reward(storage, env, pool_id, &mut cfg, address.clone(), address.clone(), net)?;
+    POOLS.save(storage, pool_id, &cfg)?;
+    update_reward_state(storage, pool_id, |state| state.liabilities = state.liabilities.saturating_sub(paid))?;
+    update_stats(storage, pool_id, |stats| stats.unbonding_count = stats.unbonding_count.saturating_sub(unbonding.len() as u64))?;
+
+    if !stake_amount.is_zero() {
+        exit.msgs.push(transfer_msg(stake_balance(&cfg, stake_amount), address.clone())?);
+    }
+    exit.msgs.extend(rewards);
+
+    // the stake is gone, so the boost lock no longer weighs anything
+    if let Some(lock) = lock {
+        BOOST_LOCKS.remove(storage, (pool_id, address.clone()));
+        if !lock.bfot.is_zero() {
+            exit.msgs.push(transfer_msg(Balance::Cw20(Cw20CoinVerified {
+                address: cfg.bfot_token_address.clone(),
+                amount: lock.bfot
+            }), address.clone())?);
+        }
+        for nft in lock.nfts {
+            exit.msgs.push(WasmMsg::Execute {
+                contract_addr: nft.collection.to_string(),
+                msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: address.to_string(),
+                    token_id: nft.token_id
+                })?,
+                funds: vec![],
+            }.into());
+        }
+    }
+
+    Ok(ForceExit {
+        msgs: exit.msgs,
+        diffs,
+        stake_amount: exit.stake_amount + stake_amount,
+        fot_amount: exit.fot_amount + net,
+        fee_amount: exit.fee_amount + paid - net
+    })
+}
+
+pub fn try_withdraw_fot(deps: DepsMut, _env: Env, info: MessageInfo, pool_id: u64) -> Result<Response, ContractError> {
+    
+    check_owner(&deps, &info, pool_id)?;
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    
+    let fot_amount = cfg.fot_amount;
+    cfg.fot_amount = Uint128::zero();
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+
+    // create transfer cw20 msg
+    let exec_transfer = transfer_msg(reward_balance(&cfg, fot_amount), info.sender.clone())?;
+
+    Ok(Response::new()
+        .add_message(exec_transfer)
+        .add_attributes(vec![
+            attr("action", "fot_withdraw_all"),
+            attr("address", info.sender.clone()),
+            attr("fot_amount", fot_amount),
+        ]))
+}
+
+pub fn try_withdraw_stake(deps: DepsMut, env: Env, info: MessageInfo, pool_id: u64) -> Result<Response, ContractError> {
+    
+    check_owner(&deps, &info, pool_id)?;
+    update_global_reward(deps.storage, &env, pool_id)?;
+
+    let mut cfg = POOLS.load(deps.storage, pool_id)?;
+    let stake_amount = cfg.staked_amount;
+    cfg.staked_amount = Uint128::zero();
+
+    POOLS.save(deps.storage, pool_id, &cfg)?;
+
+    // create transfer cw20 msg
+    let exec_transfer = transfer_msg(stake_balance(&cfg, stake_amount), info.sender.clone())?;
+
+    Ok(Response::new()
+        .add_message(exec_transfer)
+        .add_attributes(vec![
+            attr("action", "gfot_withdraw_all"),
+            attr("address", info.sender.clone()),
+            attr("stake_amount", stake_amount),
+        ]))
+}
+
+
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {pool_id} 
+            => to_json_binary(&query_config(deps, pool_id)?),
+        QueryMsg::Staker {pool_id, address} 
+            => to_json_binary(&query_staker(deps, &env, pool_id, address)?),
+        QueryMsg::ListStakers {pool_id, start_after, limit, order} 
+            => to_json_binary(&query_list_stakers(deps, pool_id, start_after, limit, order)?),
+        QueryMsg::TopStakers {pool_id, start_after, limit} 
+            => to_json_binary(&query_top_stakers(deps, pool_id, start_after, limit)?),
+        QueryMsg::Apy {pool_id} 
+            => to_json_binary(&query_apy(deps, &env, pool_id)?),
+        QueryMsg::Unstaking {pool_id, address} 
+            => to_json_binary(&query_unstaking(deps, pool_id, address)?),
+        QueryMsg::Operator {staker, operator} 
+            => to_json_binary(&query_operator(deps, env, staker, operator)?),
+        QueryMsg::NftPosition {token_id} 
+            => to_json_binary(&query_nft_position(deps, token_id)?),
+        QueryMsg::ListNftPositions {start_after, limit} 
+            => to_json_binary(&query_list_nft_positions(deps, start_after, limit)?),
+        QueryMsg::StakedBalanceAtHeight {pool_id, address, height} 
+            => to_json_binary(&query_staked_balance_at_height(deps, env, pool_id, address, height)?),
+        QueryMsg::TotalStakedAtHeight {pool_id, height} 
+            => to_json_binary(&query_total_staked_at_height(deps, env, pool_id, height)?),
+        QueryMsg::VotingPowerAtHeight {pool_id, address, height} 
+            => to_json_binary(&query_voting_power_at_height(deps, env, pool_id, address, height)?),
+        QueryMsg::TotalPowerAtHeight {pool_id, height} 
+            => to_json_binary(&query_total_power_at_height(deps, env, pool_id, height)?),
+        QueryMsg::Info {} 
+            => to_json_binary(&query_info(deps)?),
+        QueryMsg::Dao {pool_id} 
+            => to_json_binary(&query_dao(deps, pool_id)?),
+        QueryMsg::Member {pool_id, addr, at_height} 
+            => to_json_binary(&query_member(deps, pool_id, addr, at_height)?),
+        QueryMsg::ListMembers {pool_id, start_after, limit} 
+            => to_json_binary(&query_list_members(deps, pool_id, start_after, limit)?),
+        QueryMsg::TotalWeight {pool_id} 
+            => to_json_binary(&query_total_weight(deps, pool_id)?),
+        QueryMsg::Hooks {pool_id} 
+            => to_json_binary(&query_hooks(deps, pool_id)?),
+        QueryMsg::Position {pool_id, address} 
+            => to_json_binary(&query_position(deps, &env, pool_id, address)?),
+        QueryMsg::Positions {pool_id, addresses} 
+            => to_json_binary(&query_positions(deps, &env, pool_id, addresses)?),
+        QueryMsg::Stats {pool_id} 
+            => to_json_binary(&query_stats(deps, pool_id)?),
+        QueryMsg::Solvency {pool_id} 
+            => to_json_binary(&query_solvency(deps, &env, pool_id)?),
+        QueryMsg::EmissionSchedule {pool_id, start_after, limit} 
+            => to_json_binary(&query_emission_schedule(deps, &env, pool_id, start_after, limit)?),
+        QueryMsg::Vesting {pool_id, address} 
+            => to_json_binary(&query_vesting(deps, &env, pool_id, address)?),
+        QueryMsg::Boost {pool_id, address} 
+            => to_json_binary(&query_boost(deps, pool_id, address)?),
+        QueryMsg::Deposits {address, start_after, limit} 
+            => to_json_binary(&query_deposits(deps, address, start_after, limit)?),
+        QueryMsg::SfotStage {stage} 
+            => to_json_binary(&query_sfot_stage(deps, stage)?),
+        QueryMsg::LatestSfotStage {} 
+            => to_json_binary(&query_latest_sfot_stage(deps)?),
+        QueryMsg::SfotClaimed {stage, address} 
+            => to_json_binary(&query_sfot_claimed(deps, stage, address)?),
+        QueryMsg::SfotTotals {} 
+            => to_json_binary(&query_sfot_totals(deps)?),
+        QueryMsg::Admin {} 
+            => to_json_binary(&query_admin(deps)?),
+        QueryMsg::ListPools {start_after, limit} 
+            => to_json_binary(&query_list_pools(deps, start_after, limit)?),
+    }
+}
+
+pub fn query_config(deps: Deps, pool_id: u64) -> StdResult<ConfigResponse> {
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    Ok(ConfigResponse {
+        owner: cfg.owner.map(|o| o.into()),
+        fot_token_address: cfg.fot_token_address.into(),
+        bfot_token_address: cfg.bfot_token_address.into(),
+        stake_token: cfg.stake_token,
+        reward_denom: cfg.reward_denom,
+        fot_amount: cfg.fot_amount,
+        staked_amount: cfg.staked_amount,
+        daily_fot_amount: cfg.daily_fot_amount,
+        apy_prefix: cfg.apy_prefix,
+        delta_time: cfg.delta_time,
+        reward_interval: cfg.reward_interval,
+        lock_days: cfg.lock_days,
+        transfer_enabled: cfg.transfer_enabled,
+        position_nft: cfg.position_nft.map(|a| a.into()),
+        dao: cfg.dao.map(|a| a.into()),
+        price_source: cfg.price_source,
+        stake_decimals: cfg.stake_decimals,
+        underfunded_payouts: cfg.underfunded_payouts,
+        import_finalized: cfg.import_finalized,
+        term: cfg.term,
+        reserved_amount: cfg.reserved_amount,
+        deposited_amount: cfg.deposited_amount,
+        emission: cfg.emission,
+        vesting: cfg.vesting,
+        vesting_amount: cfg.vesting_amount,
+        loyalty: cfg.loyalty,
+        boost: cfg.boost,
+        fee_bps: cfg.fee_bps,
+        treasury: cfg.treasury.map(|t| t.into()),
+        fee_amount: cfg.fee_amount
+    })
+}
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+fn query_staker(deps: Deps, env: &Env, pool_id: u64, address: Addr) -> StdResult<StakerResponse> {
+    
+    let (amount, reward, last_time, sfot_reward) = stakers().may_load(deps.storage, (pool_id, address.clone()))?
+        .unwrap_or((Uint128::zero(), Uint128::zero(), 0u64, Uint128::zero()));
+    let iou = IOUS.may_load(deps.storage, (pool_id, address.clone()))?.unwrap_or_default();
+    let stake_age = STAKE_AGES.may_load(deps.storage, (pool_id, address.clone()))?.filter(|_| !amount.is_zero());
+    let reward_multiplier = match &stake_age {
+        Some(age) => {
+            let cfg = POOLS.load(deps.storage, pool_id)?;
+            stake_multiplier(deps.storage, &cfg, pool_id, &address, age.since, env.block.time.seconds())?
+        },
+        None => Decimal::one()
+    };
+    Ok(StakerResponse {
+        address,
+        amount,
+        reward,
+        last_time,
+        sfot_reward,
+        iou,
+        stake_since: stake_age.as_ref().map(|age| age.since),
+        reward_multiplier
+    })
+}
+
+
+fn query_position(deps: Deps, env: &Env, pool_id: u64, address: Addr) -> StdResult<PositionResponse> {
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let now = env.block.time.seconds();
+
+    let (staked, reward, last_time, sfot_reward) = stakers().may_load(deps.storage, (pool_id, address.clone()))?
+        .unwrap_or((Uint128::zero(), Uint128::zero(), now, Uint128::zero()));
+    let state = accrue_global_reward(&cfg, REWARD_STATE.load(deps.storage, pool_id)?, now)?;
+    let paid = REWARD_INDEX.may_load(deps.storage, (pool_id, address.clone()))?.unwrap_or_default();
+    let stake_age = STAKE_AGES.may_load(deps.storage, (pool_id, address.clone()))?.filter(|_| !staked.is_zero());
+    let (reward_multiplier, earning_multiplier) = match &stake_age {
+        Some(age) => (
+            stake_multiplier(deps.storage, &cfg, pool_id, &address, age.since, now)?,
+            average_multiplier(deps.storage, &cfg, pool_id, &address, age.since, last_time, now)?
+        ),
+        None => (Decimal::one(), Decimal::one())
+    };
+    let weight = staked + weight_bonus(staked, reward_multiplier);
+    let earning = staked + weight_bonus(staked, earning_multiplier);
+    let pending_reward = reward + earned_reward(earning, paid, state.reward_per_token)?;
+
+    let unbonding: Vec<UnbondingEntry> = UNSTAKING.may_load(deps.storage, (pool_id, address.clone()))?
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(index, (amount, unlock_time))| UnbondingEntry {
+            index: index as u64,
+            amount,
+            unlock_time,
+            matured: unlock_time <= now
+        })
+        .collect();
+    let matured_amount = unbonding.iter()
+        .filter(|entry| entry.matured)
+        .map(|entry| entry.amount)
+        .sum();
+    let next_unlock_time = unbonding.iter()
+        .filter(|entry| !entry.matured)
+        .map(|entry| entry.unlock_time)
+        .min();
+
+    let total_weight = reward_weight(&cfg, &state);
+    let pool_share = if total_weight.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::checked_from_ratio(weight, total_weight)
+            .map_err(|e| StdError::generic_err(e.to_string()))?
+    };
+
+    Ok(PositionResponse {
+        address,
+        staked,
+        unbonding,
+        matured_amount,
+        pending_reward,
+        pending_sfot_reward: sfot_reward,
+        next_unlock_time,
+        pool_share,
+        stake_since: stake_age.map(|age| age.since),
+        reward_multiplier
+    })
+}
+
+fn query_positions(deps: Deps, env: &Env, pool_id: u64, addresses: Vec<Addr>) -> StdResult<PositionsResponse> {
+    if addresses.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!("At most {} addresses per query", MAX_LIMIT)));
+    }
+    let positions:StdResult<Vec<_>> = addresses
+        .into_iter()
+        .map(|address| query_position(deps, env, pool_id, address))
+        .collect();
+
+    Ok(PositionsResponse { positions: positions? })
+}
+
+fn query_stats(deps: Deps, pool_id: u64) -> StdResult<StatsResponse> {
+    let stats = STATS.may_load(deps.storage, pool_id)?.unwrap_or_default();
+    Ok(StatsResponse {
+        staker_count: stats.staker_count,
+        unbonding_count: stats.unbonding_count,
+        fot_claimed: stats.fot_claimed,
+        fot_funded: stats.fot_funded,
+        sfot_credited: stats.sfot_credited,
+        fees_taken: stats.fees_taken,
+        fees_collected: stats.fees_collected
+    })
+}
+
+fn query_solvency(deps: Deps, env: &Env, pool_id: u64) -> StdResult<SolvencyResponse> {
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let state = accrue_global_reward(&cfg, REWARD_STATE.load(deps.storage, pool_id)?, env.block.time.seconds())?;
+    let liabilities = state.liabilities;
+    let funded = cfg.fot_amount;
+    let surplus = funded.saturating_sub(liabilities);
+
+    // the emission rate is emitted once per reward_interval
+    let daily_emission = emission_rate(&cfg, env.block.time.seconds())?.multiply_ratio(86400u64, cfg.reward_interval.max(1));
+    let runway_days = if daily_emission.is_zero() {
+        None
+    } else {
+        Some(u64::try_from((surplus / daily_emission).u128()).unwrap_or(u64::MAX))
+    };
+
+    Ok(SolvencyResponse {
+        liabilities,
+        funded,
+        surplus,
+        deficit: liabilities.saturating_sub(funded),
+        runway_days
+    })
+}
+
+fn query_sfot_stage(deps: Deps, stage: u8) -> StdResult<SfotStageResponse> {
+    let sfot_stage = SFOT_STAGES.load(deps.storage, stage)?;
+    Ok(SfotStageResponse {
+        stage,
+        merkle_root: sfot_stage.merkle_root,
+        sfot_token_address: sfot_stage.sfot_token_address.into(),
+        total_amount: sfot_stage.total_amount,
+        claimed_amount: sfot_stage.claimed_amount,
+        start: sfot_stage.start,
+        expiration: sfot_stage.expiration,
+        reclaimed: sfot_stage.reclaimed
+    })
+}
+
+fn query_latest_sfot_stage(deps: Deps) -> StdResult<LatestSfotStageResponse> {
+    let latest_stage = LATEST_SFOT_STAGE.may_load(deps.storage)?.unwrap_or_default();
+    Ok(LatestSfotStageResponse { latest_stage })
+}
+
+fn query_sfot_claimed(deps: Deps, stage: u8, address: String) -> StdResult<SfotClaimedResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let claimed = SFOT_CLAIMED.may_load(deps.storage, (stage, address))?.unwrap_or(false);
+    Ok(SfotClaimedResponse { claimed })
+}
+
+fn query_sfot_totals(deps: Deps) -> StdResult<SfotTotalsResponse> {
+    let totals = SFOT_TOTALS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(SfotTotalsResponse {
+        registered: totals.registered,
+        claimed: totals.claimed,
+        reclaimed: totals.reclaimed
+    })
+}
+
+fn query_unstaking(deps: Deps, pool_id: u64, address: Addr) -> StdResult<Vec<(Uint128, u64)>> {
+    
+    let unstaking = UNSTAKING.may_load(deps.storage, (pool_id, address))?.unwrap_or_default();
+    Ok(unstaking)
+}
+
+fn query_operator(deps: Deps, env: Env, staker: Addr, operator: Addr) -> StdResult<OperatorResponse> {
+    
+    let expires = OPERATORS.may_load(deps.storage, (staker.clone(), operator.clone()))?;
+    Ok(OperatorResponse {
+        staker,
+        operator,
+        approved: expires.is_some_and(|e| !e.is_expired(&env.block)),
+        expires: expires.unwrap_or_default()
+    })
+}
+
+fn map_staker(
+    item: StdResult<(Addr, StakerData)>,
+) -> StdResult<StakerInfo> {
+    item.map(|(address, (amount, reward, last_time, sfot_reward))| {
+        StakerInfo {
+            address,
+            amount,
+            reward,
+            last_time,
+            sfot_reward
+        }
+    })
+}
+
+fn map_nft_position(
+    item: StdResult<(u64, Position)>,
+) -> StdResult<NftPositionResponse> {
+    item.map(|(id, position)| {
+        NftPositionResponse {
+            token_id: id.to_string(),
+            amount: position.amount,
+            lock_days: position.lock_days,
+            start_time: position.start_time,
+            reward: position.reward,
+            last_time: position.last_time
+        }
+    })
+}
+
+fn query_emission_schedule(
+    deps: Deps,
+    env: &Env,
+    pool_id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<EmissionScheduleResponse> {
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let segments = match &cfg.emission {
+        None => vec![],
+        Some(EmissionSchedule::Segments { segments }) => segments.iter()
+            .filter(|segment| !matches!(start_after, Some(time) if segment.start_time <= time))
+            .take(limit)
+            .cloned()
+            .collect(),
+        Some(EmissionSchedule::Decay { start_time, initial_rate, period, factor }) => {
+            let first = match start_after {
+                Some(time) if time >= *start_time => (time - start_time) / period + 1,
+                _ => 0
+            };
+            (first..first + limit as u64)
+                .map_while(|k| Some(EmissionSegment {
+                    start_time: k.checked_mul(*period)?.checked_add(*start_time)?,
+                    rate: decayed_rate(*initial_rate, *factor, k)
+                }))
+                .collect()
+        }
+    };
+
+    Ok(EmissionScheduleResponse {
+        current_rate: emission_rate(&cfg, env.block.time.seconds())?,
+        schedule: cfg.emission,
+        segments
+    })
+}
+
+fn query_vesting(deps: Deps, env: &Env, pool_id: u64, address: String) -> StdResult<VestingResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let now = env.block.time.seconds();
+    let entries = VESTING.may_load(deps.storage, (pool_id, address.clone()))?.unwrap_or_default();
+
+    let mut amount = Uint128::zero();
+    let mut vested = Uint128::zero();
+    for entry in &entries {
+        amount += entry.amount - entry.withdrawn;
+        vested += vested_amount(entry, now) - entry.withdrawn;
+    }
+
+    Ok(VestingResponse { address, amount, vested, entries })
+}
+
+fn query_boost(deps: Deps, pool_id: u64, address: String) -> StdResult<BoostResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let lock = BOOST_LOCKS.may_load(deps.storage, (pool_id, address.clone()))?.unwrap_or_default();
+
+    Ok(BoostResponse {
+        address,
+        boost_multiplier: boost_multiplier(&cfg, &lock),
+        bfot: lock.bfot,
+        nfts: lock.nfts
+    })
+}
+
+fn query_deposits(
+    deps: Deps,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DepositListResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let deposits:StdResult<Vec<_>> = DEPOSITS
+        .prefix(address)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(id, deposit)| DepositResponse {
+            id,
+            pool_id: deposit.pool_id,
+            amount: deposit.amount,
+            reward: deposit.reward,
+            start_time: deposit.start_time,
+            maturity: deposit.maturity
+        }))
+        .collect();
+
+    Ok(DepositListResponse { deposits: deposits? })
+}
+
+fn query_nft_position(deps: Deps, token_id: String) -> StdResult<NftPositionResponse> {
+    let id = token_id.parse::<u64>().map_err(|_| StdError::generic_err("Invalid token id"))?;
+    let position = positions().load(deps.storage, id)?;
+    map_nft_position(Ok((id, position)))
+}
+
+fn query_list_nft_positions(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<NftPositionListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(token_id) => Some(Bound::exclusive(
+            token_id.parse::<u64>().map_err(|_| StdError::generic_err("Invalid token id"))?
+        )),
+        None => None
+    };
+
+    let positions:StdResult<Vec<_>> = positions()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(map_nft_position)
+        .collect();
+
+    Ok(NftPositionListResponse { positions: positions? })
+}
+
+fn query_list_stakers(
+    deps: Deps,
+    pool_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<StakerListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let bound = addr.map(Bound::exclusive);
+    let order = order.unwrap_or(OrderBy::Asc);
+    let (start, end) = match order {
+        OrderBy::Asc => (bound, None),
+        OrderBy::Desc => (None, bound)
+    };
+
+    let stakers:StdResult<Vec<_>> = stakers()
+        .prefix(pool_id)
+        .range(deps.storage, start, end, order.into())
+        .take(limit)
+        .map(map_staker)
+        .collect();
+
+    Ok(StakerListResponse { stakers: stakers? })
+}
+
+fn query_top_stakers(
+    deps: Deps,
+    pool_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<StakerListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let end = match addr {
+        Some(addr) => {
+            let (amount, _, _, _) = stakers().load(deps.storage, (pool_id, addr.clone()))?;
+            Some(Bound::exclusive((amount_index_key(amount), (pool_id, addr))))
+        },
+        None => None
+    };
+
+    // stakers that fully unstaked sort last and are left out
+    let stakers:StdResult<Vec<_>> = stakers()
+        .idx
+        .amount
+        .sub_prefix(pool_id)
+        .range(deps.storage, None, end, Order::Descending)
+        .map(|item| item.map(|((_, addr), staker)| (addr, staker)))
+        .take_while(|item| item.as_ref().map_or(true, |(_, staker)| !staker.0.is_zero()))
+        .take(limit)
+        .map(map_staker)
+        .collect();
+
+    Ok(StakerListResponse { stakers: stakers? })
+}
+
+pub fn query_staked_balance_at_height(
+    deps: Deps,
+    env: Env,
+    pool_id: u64,
+    address: Addr,
+    height: Option<u64>
+) -> StdResult<StakedBalanceAtHeightResponse> {
+    let height = height.unwrap_or(env.block.height);
+    let balance = STAKED_BALANCES
+        .may_load_at_height(deps.storage, (pool_id, address), height)?
+        .unwrap_or_default();
+    Ok(StakedBalanceAtHeightResponse { balance, height })
+}
+
+pub fn query_total_staked_at_height(
+    deps: Deps,
+    env: Env,
+    pool_id: u64,
+    height: Option<u64>
+) -> StdResult<TotalStakedAtHeightResponse> {
+    let height = height.unwrap_or(env.block.height);
+    let total = STAKED_TOTAL
+        .may_load_at_height(deps.storage, pool_id, height)?
+        .unwrap_or_default();
+    Ok(TotalStakedAtHeightResponse { total, height })
+}
+
+pub fn query_voting_power_at_height(
+    deps: Deps,
+    env: Env,
+    pool_id: u64,
+    address: String,
+    height: Option<u64>
+) -> StdResult<VotingPowerAtHeightResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let res = query_staked_balance_at_height(deps, env, pool_id, address, height)?;
+    Ok(VotingPowerAtHeightResponse { power: res.balance, height: res.height })
+}
+
+pub fn query_total_power_at_height(
+    deps: Deps,
+    env: Env,
+    pool_id: u64,
+    height: Option<u64>
+) -> StdResult<TotalPowerAtHeightResponse> {
+    let res = query_total_staked_at_height(deps, env, pool_id, height)?;
+    Ok(TotalPowerAtHeightResponse { power: res.total, height: res.height })
+}
+
+pub fn query_info(deps: Deps) -> StdResult<InfoResponse> {
+    let info = get_contract_version(deps.storage)?;
+    Ok(InfoResponse { info })
+}
+
+pub fn query_dao(deps: Deps, pool_id: u64) -> StdResult<Addr> {
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    cfg.dao.ok_or_else(|| StdError::not_found("dao"))
+}
+
+pub fn query_member(deps: Deps, pool_id: u64, addr: String, at_height: Option<u64>) -> StdResult<MemberResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let amount = match at_height {
+        Some(height) => STAKED_BALANCES.may_load_at_height(deps.storage, (pool_id, addr), height)?,
+        None => STAKED_BALANCES.may_load(deps.storage, (pool_id, addr))?
+    };
+    Ok(MemberResponse { weight: member_weight(amount.unwrap_or_default()) })
+}
+
+pub fn query_list_members(
+    deps: Deps,
+    pool_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<MemberListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let start = addr.map(Bound::exclusive);
+
+    // balances are removed when they drop to zero, so every entry is a member
+    let members: StdResult<Vec<_>> = STAKED_BALANCES.prefix(pool_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(addr, amount)| Member {
+            addr: addr.into(),
+            weight: member_weight(amount).unwrap_or_default()
+        }))
+        .collect();
+
+    Ok(MemberListResponse { members: members? })
+}
+
+pub fn query_total_weight(deps: Deps, pool_id: u64) -> StdResult<TotalWeightResponse> {
+    let total = STAKED_TOTAL.may_load(deps.storage, pool_id)?.unwrap_or_default();
+    Ok(TotalWeightResponse { weight: member_weight(total).unwrap_or_default() })
+}
+
+pub fn query_admin(deps: Deps) -> StdResult<AdminResponse> {
+    let admin = ADMIN.may_load(deps.storage)?.flatten();
+    Ok(AdminResponse { admin: admin.map(|a| a.into()) })
+}
+
+pub fn query_list_pools(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PoolListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let pool_ids:StdResult<Vec<_>> = POOLS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect();
+    let pools:StdResult<Vec<_>> = pool_ids?
+        .into_iter()
+        .map(|pool_id| Ok(PoolConfigResponse { pool_id, config: query_config(deps, pool_id)? }))
+        .collect();
+
+    Ok(PoolListResponse { pools: pools? })
+}
+
+pub fn query_hooks(deps: Deps, pool_id: u64) -> StdResult<HooksResponse> {
+    let hooks = HOOKS.may_load(deps.storage, pool_id)?.unwrap_or_default();
+    Ok(HooksResponse { hooks: hooks.into_iter().map(|hook| hook.into()).collect() })
+}
+
+/// Price of one whole stake token from the configured price source
+fn query_stake_price(deps: Deps, cfg: &Config) -> StdResult<Decimal> {
+    match &cfg.price_source {
+        PriceSource::BondingCurve {} => {
+            let contract_addr = match &cfg.stake_token {
+                AssetInfo::Token { contract_addr } => contract_addr.clone(),
+                AssetInfo::NativeToken { .. } => return Err(StdError::generic_err("Bonding curve needs a cw20 stake token"))
+            };
+            let gfot_token_info: TokenInfoResponse =
+                deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr,
+                    msg: to_json_binary(&Cw20QueryMsg::TokenInfo {})?,
+                }))?;
+
+            Decimal::checked_from_ratio(gfot_token_info.total_supply, pow10(cfg.stake_decimals)?)
+                .map_err(|e| StdError::generic_err(e.to_string()))?
+                .checked_add(Decimal::from_ratio(10000u128, 1u128))
+                .map_err(StdError::from)
+        },
+        PriceSource::Fixed { price } => Ok(*price),
+        PriceSource::PairPool { contract_addr, decimals } => {
+            let pool: PoolResponse = deps.querier.query_wasm_smart(contract_addr, &PairQueryMsg::Pool {})?;
+            let (staked, other) = match &pool.assets {
+                [a, b] if a.info == cfg.stake_token => (a, b),
+                [a, b] if b.info == cfg.stake_token => (b, a),
+                _ => return Err(StdError::generic_err("Pair does not hold the stake token"))
+            };
+
+            // other reserve / 10^decimals per stake token reserve / 10^stake_decimals
+            let price = Decimal256::checked_from_ratio(
+                other.amount.full_mul(pow10(cfg.stake_decimals)?),
+                staked.amount.full_mul(pow10(*decimals)?)
+            ).map_err(|e| StdError::generic_err(e.to_string()))?;
+            Decimal::try_from(price).map_err(|e| StdError::generic_err(e.to_string()))
+        },
+        PriceSource::PairSimulation { contract_addr, decimals } => {
+            let sim: SimulationResponse = deps.querier.query_wasm_smart(contract_addr, &PairQueryMsg::Simulation {
+                offer_asset: Asset {
+                    info: cfg.stake_token.clone(),
+                    amount: pow10(cfg.stake_decimals)?
+                }
+            })?;
+            Decimal::checked_from_ratio(sim.return_amount, pow10(*decimals)?)
+                .map_err(|e| StdError::generic_err(e.to_string()))
+        }
+    }
+}
+
+fn pow10(decimals: u8) -> StdResult<Uint128> {
+    Ok(Uint128::from(10u128).checked_pow(decimals as u32)?)
+}
+
+pub fn query_apy(deps: Deps, env: &Env, pool_id: u64) -> StdResult<ApyResponse> {
+    let cfg = POOLS.load(deps.storage, pool_id)?;
+    let total_staked = cfg.staked_amount;
+
+    let periods = SECONDS_PER_YEAR.checked_div(cfg.reward_interval)
+        .ok_or_else(|| StdError::generic_err("Reward interval is zero"))?;
+    let yearly_emission = emission_rate(&cfg, env.block.time.seconds())?.checked_mul(Uint128::from(periods))?;
+    let stake_price = query_stake_price(deps, &cfg)?;
+
+    // apy_prefix is the yearly emission valued in bFOT, the stake is valued with stake_price
+    let mut apr = Decimal::zero();
+    if !total_staked.is_zero() {
+        let staked_value = Decimal::checked_from_ratio(total_staked, pow10(cfg.stake_decimals)?)
+            .map_err(|e| StdError::generic_err(e.to_string()))?
+            .checked_mul(stake_price)?;
+        if staked_value.is_zero() {
+            return Err(StdError::generic_err("Stake has no value"));
+        }
+        apr = Decimal::from_ratio(cfg.apy_prefix, 1u128)
+            .checked_div(staked_value)
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+    }
+
+    // rewards compounded once per reward interval
+    let apy = if periods == 0 {
+        Decimal256::from(apr)
+    } else {
+        let exp = u32::try_from(periods).map_err(|_| StdError::generic_err("Too many reward periods"))?;
+        let rate = Decimal256::from(apr)
+            .checked_div(Decimal256::from_ratio(periods, 1u128))
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+        Decimal256::one().checked_add(rate)?.checked_pow(exp)? - Decimal256::one()
+    };
+
+    Ok(ApyResponse {
+        apr,
+        apy,
+        yearly_emission,
+        total_staked,
+        stake_price
+    })
+}
+
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version = get_contract_version(deps.storage)?;
+    if version.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: version.contract,
+        });
+    }
+
+    // make the single pool contract pool 0. Its stakers, unstaking lists and positions are
+    // moved by MigrateStakers or when first touched, this stays within a block's gas.
+    // Stakes age from the migration on, the single pool contract kept no stake ages.
+    let migration = LEGACY_MIGRATION.may_load(deps.storage)?;
+    if let (Some(cfg), None) = (CONFIG.may_load(deps.storage)?, migration) {
+        let now = env.block.time.seconds();
+        ADMIN.save(deps.storage, &cfg.owner)?;
+        POOL_COUNT.save(deps.storage, &1u64)?;
+        // staked_amount still counts the legacy stakes, the reward index starts from zero
+        save_new_pool(deps.storage, &env, 0, &cfg)?;
+        LEGACY_MIGRATION.save(deps.storage, &LegacyMigration { time: now, last_position: None })?;
+    }
+    Ok(Response::default())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coin, ContractResult, SystemResult};
+    use cw_utils::PaymentError;
+
+    fn instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            owner: None,
+            fot_token_address: Addr::unchecked("fot"),
+            bfot_token_address: Addr::unchecked("bfot"),
+            stake_token: AssetInfo::Token { contract_addr: "gfot".to_string() },
+            reward_denom: None,
+            daily_fot_amount: Uint128::from(1000u128),
+            apy_prefix: Uint128::zero(),
+            delta_time: 0,
+            reward_interval: 86400,
+            lock_days: 14,
+            transfer_enabled: false,
+            dao: None,
+            price_source: None,
+            stake_decimals: None,
+            term: None,
+            emission: None,
+            vesting: None,
+            loyalty: None,
+            boost: None,
+            fee_bps: 0,
+            treasury: None
+        }
+    }
+
+    fn receive(sender: &str, amount: u128, msg: &ReceiveMsg) -> ExecuteMsg {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_json_binary(msg).unwrap()
+        })
+    }
+
+    #[test]
+    fn member_weight_saturates() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::AddHook {
+            pool_id: 0,
+            addr: "hook".to_string()
+        }).unwrap();
+
+        let amount = u64::MAX as u128 + 1;
+        let res = execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", amount, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let member: MemberResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::Member {
+            pool_id: 0,
+            addr: "alice".to_string(),
+            at_height: None
+        }).unwrap()).unwrap();
+        assert_eq!(member.weight, Some(u64::MAX));
+        let total: TotalWeightResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::TotalWeight { pool_id: 0 }).unwrap()).unwrap();
+        assert_eq!(total.weight, u64::MAX);
+    }
+
+    #[test]
+    fn ious_settle_pro_rata() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        set_iou(deps.as_mut().storage, 0, Addr::unchecked("alice"), Uint128::from(300u128)).unwrap();
+        set_iou(deps.as_mut().storage, 0, Addr::unchecked("bob"), Uint128::from(100u128)).unwrap();
+        update_reward_state(deps.as_mut().storage, 0, |state| state.liabilities = Uint128::from(400u128)).unwrap();
+
+        // half of all IOUs is covered, so each is half paid
+        let res = execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 200, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(IOUS.load(&deps.storage, (0, Addr::unchecked("alice"))).unwrap(), Uint128::from(150u128));
+        assert_eq!(IOUS.load(&deps.storage, (0, Addr::unchecked("bob"))).unwrap(), Uint128::from(50u128));
+        let state = REWARD_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!((state.ious, state.liabilities), (Uint128::from(200u128), Uint128::from(200u128)));
+
+        // funding more than owed settles them and leaves the rest in the pool
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 500, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        assert_eq!(IOUS.may_load(&deps.storage, (0, Addr::unchecked("alice"))).unwrap(), None);
+        assert_eq!(REWARD_STATE.load(&deps.storage, 0).unwrap().ious, Uint128::zero());
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().fot_amount, Uint128::from(300u128));
+    }
+
+    #[test]
+    fn receive_dispatches_on_message() {
+        // a pool staking the FOT token it is funded with
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            stake_token: AssetInfo::Token { contract_addr: "fot".to_string() },
+            ..instantiate_msg()
+        }).unwrap();
+
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 500, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        let cfg = POOLS.load(&deps.storage, 0).unwrap();
+        assert_eq!((cfg.fot_amount, cfg.staked_amount), (Uint128::from(500u128), Uint128::zero()));
+
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("alice", 300, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        let cfg = POOLS.load(&deps.storage, 0).unwrap();
+        assert_eq!((cfg.fot_amount, cfg.staked_amount), (Uint128::from(500u128), Uint128::from(300u128)));
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("alice", 100, &ReceiveMsg::Boost { pool_id: 0 })).unwrap_err();
+        assert_eq!(err, ContractError::UnacceptableToken {});
+
+        // a message older senders attach that is not a ReceiveMsg is treated as an empty one
+        let res = execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "alice".to_string(),
+            amount: Uint128::from(200u128),
+            msg: Binary::from(b"{\"stake\":\"all\"}".to_vec())
+        })).unwrap();
+        assert!(res.attributes.contains(&attr("action", "stake")));
+        let cfg = POOLS.load(&deps.storage, 0).unwrap();
+        assert_eq!((cfg.fot_amount, cfg.staked_amount), (Uint128::from(500u128), Uint128::from(500u128)));
+    }
+
+    #[test]
+    fn apy_uses_stake_decimals() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            apy_prefix: Uint128::from(1u128),
+            price_source: Some(PriceSource::Fixed { price: Decimal::from_ratio(2u128, 1u128) }),
+            stake_decimals: Some(6),
+            ..instantiate_msg()
+        }).unwrap();
+        assert_eq!(query_config(deps.as_ref(), 0).unwrap().stake_decimals, 6);
+
+        // one whole stake token worth 2
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 1_000_000, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        let apy = query_apy(deps.as_ref(), &mock_env(), 0).unwrap();
+        assert_eq!(apy.apr, Decimal::percent(50));
+
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            stake_decimals: Some(39),
+            ..instantiate_msg()
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn deposits_exit_and_redeem_while_disabled() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            term: Some(Term { days: 30, rate: Decimal::percent(10) }),
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 1000, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        for (depositor, amount) in [("alice", 1000), ("bob", 500), ("carol", 200)] {
+            execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive(depositor, amount, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        }
+        let pool = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+            let cfg = POOLS.load(&deps.storage, 0).unwrap();
+            (cfg.fot_amount.u128(), cfg.reserved_amount.u128(), cfg.deposited_amount.u128())
+        };
+        assert_eq!(pool(&deps), (830, 170, 1700));
+
+        // half way through the term alice gets her principal and half her reward
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(15 * 86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::ForceExit {
+            pool_id: 0,
+            address: "alice".to_string()
+        }).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(pool(&deps), (880, 70, 700));
+
+        env.block.time = env.block.time.plus_seconds(15 * 86400);
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::UpdateConstants {
+            pool_id: 0,
+            daily_fot_amount: Uint128::from(1000u128),
+            apy_prefix: Uint128::zero(),
+            reward_interval: 86400,
+            delta_time: 0,
+            lock_days: 14,
+            enabled: false
+        }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), ExecuteMsg::Redeem { pool_id: 0, id: 2 }).unwrap();
+        assert_eq!(pool(&deps), (880, 20, 200));
+
+        // carol has no staker row, purging still reaches her deposit
+        let res = execute(deps.as_mut(), env, mock_info("owner", &[]), ExecuteMsg::PurgeStakers {
+            pool_id: 0,
+            start_after: None,
+            limit: None
+        }).unwrap();
+        let purged: PurgeStakersResponse = from_json(res.data.unwrap()).unwrap();
+        assert_eq!((purged.purged, purged.next_start_after), (1, None));
+        assert_eq!(pool(&deps), (880, 0, 0));
+        assert!(DEPOSITS.is_empty(&deps.storage));
+    }
+
+    #[test]
+    fn decay_is_bounded() {
+        let decay = |period: u64, factor: Decimal| EmissionSchedule::Decay {
+            start_time: 0,
+            initial_rate: Uint128::from(1_000_000u128),
+            period,
+            factor
+        };
+        assert!(validate_emission_schedule(decay(86400, Decimal::percent(50)), 86400).is_ok());
+        // shorter than the reward interval
+        assert!(validate_emission_schedule(decay(3600, Decimal::percent(50)), 86400).is_err());
+        // still emitting after MAX_DECAY_PERIODS
+        assert!(validate_emission_schedule(decay(86400, Decimal::permille(999)), 86400).is_err());
+
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            emission: Some(decay(86400, Decimal::percent(50))),
+            ..instantiate_msg()
+        }).unwrap();
+        let update = |reward_interval: u64| ExecuteMsg::UpdateConstants {
+            pool_id: 0,
+            daily_fot_amount: Uint128::from(1000u128),
+            apy_prefix: Uint128::zero(),
+            reward_interval,
+            delta_time: 0,
+            lock_days: 14,
+            enabled: true
+        };
+        assert!(execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update(2 * 86400)).is_err());
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update(3600)).unwrap();
+    }
+
+    #[test]
+    fn vesting_is_keyed_by_staker() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            vesting: Some(Vesting { duration: 10 * 86400, cliff: 0 }),
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("bob", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 5000, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+
+        // a recipient at claim time does not reach into alice's vesting
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), ExecuteMsg::ClaimReward {
+            pool_id: 0,
+            staker: None,
+            recipient: Some("alice".to_string())
+        }).unwrap();
+        assert!(res.messages.is_empty());
+        assert!(VESTING.may_load(&deps.storage, (0, Addr::unchecked("alice"))).unwrap().is_none());
+        assert_eq!(VESTING.load(&deps.storage, (0, Addr::unchecked("bob"))).unwrap()[0].amount, Uint128::from(1000u128));
+
+        // IOUs settle into vesting as well
+        set_iou(deps.as_mut().storage, 0, Addr::unchecked("carol"), Uint128::from(300u128)).unwrap();
+        update_reward_state(deps.as_mut().storage, 0, |state| state.liabilities += Uint128::from(300u128)).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), mock_info("fot", &[]), receive("owner", 100, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(VESTING.load(&deps.storage, (0, Addr::unchecked("carol"))).unwrap()[0].amount, Uint128::from(300u128));
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().vesting_amount, Uint128::from(1300u128));
+
+        // bob picks the recipient when withdrawing, also from a disabled pool
+        env.block.time = env.block.time.plus_seconds(10 * 86400);
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::UpdateConstants {
+            pool_id: 0,
+            daily_fot_amount: Uint128::from(1000u128),
+            apy_prefix: Uint128::zero(),
+            reward_interval: 86400,
+            delta_time: 0,
+            lock_days: 14,
+            enabled: false
+        }).unwrap();
+        let res = execute(deps.as_mut(), env, mock_info("bob", &[]), ExecuteMsg::WithdrawVested {
+            pool_id: 0,
+            early: false,
+            recipient: Some("alice".to_string())
+        }).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert!(res.attributes.contains(&attr("recipient", "alice")));
+    }
+
+    #[test]
+    fn force_exit_and_forfeit_take_fee() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            fee_bps: 1000,
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("bob", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 5000, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::ForceExit {
+            pool_id: 0,
+            address: "bob".to_string()
+        }).unwrap();
+        assert!(res.attributes.contains(&attr("fot_amount", "900")));
+        assert!(res.attributes.contains(&attr("fee_amount", "100")));
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().fee_amount, Uint128::from(100u128));
+
+        // 900 vests after the fee, half of it is forfeited at half time
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::SetVesting {
+            pool_id: 0,
+            vesting: Some(Vesting { duration: 10 * 86400, cliff: 0 })
+        }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("gfot", &[]), receive("bob", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), ExecuteMsg::ClaimReward { pool_id: 0, staker: None, recipient: None }).unwrap();
+        env.block.time = env.block.time.plus_seconds(5 * 86400);
+        let res = execute(deps.as_mut(), env, mock_info("bob", &[]), ExecuteMsg::WithdrawVested {
+            pool_id: 0,
+            early: true,
+            recipient: None
+        }).unwrap();
+        assert!(res.attributes.contains(&attr("forfeited_amount", "450")));
+        assert!(res.attributes.contains(&attr("fee_amount", "45")));
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().fee_amount, Uint128::from(245u128));
+    }
+
+    fn pair_config(price_source: PriceSource) -> Config {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            price_source: Some(price_source),
+            ..instantiate_msg()
+        }).unwrap();
+        POOLS.load(&deps.storage, 0).unwrap()
+    }
+
+    #[test]
+    fn stake_price_from_pair_pool() {
+        let cfg = pair_config(PriceSource::PairPool { contract_addr: Addr::unchecked("pair"), decimals: 6 });
+        let gfot = Asset { info: cfg.stake_token.clone(), amount: Uint128::from(10_000_000_000u128) };
+        let juno = Asset { info: AssetInfo::NativeToken { denom: "ujuno".to_string() }, amount: Uint128::from(2_000_000u128) };
+
+        // either asset order, 1 gFOT (10 decimals) against 2 JUNO (6 decimals)
+        for assets in [[juno.clone(), gfot.clone()], [gfot, juno]] {
+            let mut deps = mock_dependencies();
+            deps.querier.update_wasm(move |query| match query {
+                WasmQuery::Smart { contract_addr, msg } if contract_addr == "pair" => {
+                    assert_eq!(from_json::<PairQueryMsg>(msg).unwrap(), PairQueryMsg::Pool {});
+                    SystemResult::Ok(ContractResult::Ok(to_json_binary(&PoolResponse {
+                        assets: assets.clone(),
+                        total_share: Uint128::zero()
+                    }).unwrap()))
+                },
+                _ => panic!("unexpected query")
+            });
+            assert_eq!(query_stake_price(deps.as_ref(), &cfg).unwrap(), Decimal::from_ratio(2u128, 1u128));
+        }
+
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|_| SystemResult::Ok(ContractResult::Ok(to_json_binary(&PoolResponse {
+            assets: [
+                Asset { info: AssetInfo::NativeToken { denom: "ujuno".to_string() }, amount: Uint128::from(1u128) },
+                Asset { info: AssetInfo::NativeToken { denom: "uatom".to_string() }, amount: Uint128::from(1u128) }
+            ],
+            total_share: Uint128::zero()
+        }).unwrap())));
+        assert!(query_stake_price(deps.as_ref(), &cfg).is_err());
+    }
+
+    #[test]
+    fn stake_price_from_pair_simulation() {
+        let cfg = pair_config(PriceSource::PairSimulation { contract_addr: Addr::unchecked("pair"), decimals: 6 });
+        let stake_token = cfg.stake_token.clone();
+
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "pair" => {
+                // offers one gFOT
+                assert_eq!(from_json::<PairQueryMsg>(msg).unwrap(), PairQueryMsg::Simulation {
+                    offer_asset: Asset { info: stake_token.clone(), amount: Uint128::from(10_000_000_000u128) }
+                });
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&SimulationResponse {
+                    return_amount: Uint128::from(1_500_000u128),
+                    spread_amount: Uint128::from(10_000u128),
+                    commission_amount: Uint128::from(4_500u128)
+                }).unwrap()))
+            },
+            _ => panic!("unexpected query")
+        });
+        assert_eq!(query_stake_price(deps.as_ref(), &cfg).unwrap(), Decimal::from_ratio(3u128, 2u128));
+    }
+
+    /// Recipient and amount of a cw20 transfer of `token`
+    fn cw20_transfer(msg: &CosmosMsg, token: &str) -> (String, Uint128) {
+        match msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) if contract_addr == token => {
+                match from_json(msg).unwrap() {
+                    Cw20ExecuteMsg::Transfer { recipient, amount } => (recipient, amount),
+                    other => panic!("unexpected cw20 message {:?}", other)
+                }
+            },
+            other => panic!("unexpected message {:?}", other)
+        }
+    }
+
+    #[test]
+    fn operators_claim_to_recipients() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 5000, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let claim = |recipient: Option<&str>| ExecuteMsg::ClaimReward {
+            pool_id: 0,
+            staker: Some("alice".to_string()),
+            recipient: recipient.map(|r| r.to_string())
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), claim(None)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::ApproveOperator {
+            operator: "bob".to_string(),
+            expires: Some(Expiration::AtHeight(env.block.height + 10))
+        }).unwrap();
+        let operator: OperatorResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::Operator {
+            staker: Addr::unchecked("alice"),
+            operator: Addr::unchecked("bob")
+        }).unwrap()).unwrap();
+        assert!(operator.approved);
+
+        // bob claims alice's reward to carol
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), claim(Some("carol"))).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "fot"), ("carol".to_string(), Uint128::from(1000u128)));
+        assert!(res.attributes.contains(&attr("address", "alice")));
+
+        // alice claims to herself when no recipient is set
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::ClaimReward {
+            pool_id: 0,
+            staker: None,
+            recipient: None
+        }).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "fot"), ("alice".to_string(), Uint128::from(1000u128)));
+
+        // expired and revoked approvals are rejected
+        env.block.time = env.block.time.plus_seconds(86400);
+        env.block.height += 10;
+        let err = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), claim(None)).unwrap_err();
+        assert_eq!(err, ContractError::Expired {});
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::RevokeOperator { operator: "bob".to_string() }).unwrap();
+        let err = execute(deps.as_mut(), env, mock_info("bob", &[]), claim(None)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn transfer_stake_moves_stake_and_reward() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 400, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let transfer = |amount: u128, include_reward: bool| ExecuteMsg::TransferStake {
+            pool_id: 0,
+            recipient: "bob".to_string(),
+            amount: Uint128::from(amount),
+            include_reward: Some(include_reward)
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), transfer(100, false)).unwrap_err();
+        assert_eq!(err, ContractError::TransferDisabled {});
+
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::SetTransferEnabled { pool_id: 0, enabled: true }).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), transfer(500, false)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughgFOT {});
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), transfer(100, true)).unwrap();
+
+        let staker = |address: &str| query_staker(deps.as_ref(), &mock_env(), 0, Addr::unchecked(address)).unwrap();
+        assert_eq!((staker("alice").amount, staker("alice").reward), (Uint128::from(300u128), Uint128::zero()));
+        assert_eq!((staker("bob").amount, staker("bob").reward), (Uint128::from(100u128), Uint128::from(1000u128)));
+        // the pool total is unchanged, only the balances moved
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().staked_amount, Uint128::from(400u128));
+        // snapshots taken at a height are read from the next one
+        let balance = |address: &str| query_staked_balance_at_height(deps.as_ref(), env.clone(), 0, Addr::unchecked(address), Some(env.block.height + 1)).unwrap().balance;
+        assert_eq!((balance("alice"), balance("bob")), (Uint128::from(300u128), Uint128::from(100u128)));
+
+        // the next interval is earned pro rata by the new amounts
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), transfer(50, false)).unwrap();
+        let staker = |address: &str| query_staker(deps.as_ref(), &mock_env(), 0, Addr::unchecked(address)).unwrap();
+        assert_eq!(staker("alice").reward, Uint128::from(750u128));
+        assert_eq!(staker("bob").reward, Uint128::from(1250u128));
+    }
+
+    #[test]
+    fn snapshots_follow_heights() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let height = env.block.height;
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+
+        let mut later = env.clone();
+        later.block.height += 5;
+        execute(deps.as_mut(), later.clone(), mock_info("gfot", &[]), receive("bob", 50, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), later.clone(), mock_info("alice", &[]), ExecuteMsg::CreateUnstake {
+            pool_id: 0,
+            unstake_amount: Uint128::from(40u128),
+            staker: None
+        }).unwrap();
+
+        let balance = |address: &str, at: u64| query_staked_balance_at_height(deps.as_ref(), later.clone(), 0, Addr::unchecked(address), Some(at)).unwrap().balance.u128();
+        let total = |at: u64| query_total_staked_at_height(deps.as_ref(), later.clone(), 0, Some(at)).unwrap().total.u128();
+        // a change shows from the block after it
+        assert_eq!((balance("alice", height), balance("bob", height), total(height)), (0, 0, 0));
+        assert_eq!((balance("alice", height + 1), balance("bob", height + 1), total(height + 1)), (100, 0, 100));
+        assert_eq!((balance("alice", height + 5), total(height + 5)), (100, 100));
+        assert_eq!((balance("alice", height + 6), balance("bob", height + 6), total(height + 6)), (60, 50, 110));
+
+        // without a height the current block is read
+        let res = query_staked_balance_at_height(deps.as_ref(), later.clone(), 0, Addr::unchecked("alice"), None).unwrap();
+        assert_eq!((res.balance.u128(), res.height), (100, height + 5));
+    }
+
+    #[test]
+    fn dao_voting_module_queries() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            dao: Some("dao".to_string()),
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+
+        let mut env = mock_env();
+        env.block.height += 1;
+        let power = query(deps.as_ref(), env.clone(), QueryMsg::VotingPowerAtHeight {
+            pool_id: 0,
+            address: "alice".to_string(),
+            height: None
+        }).unwrap();
+        assert_eq!(power, Binary::from(format!(r#"{{"power":"100","height":{}}}"#, env.block.height).as_bytes()));
+        let total: TotalPowerAtHeightResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::TotalPowerAtHeight {
+            pool_id: 0,
+            height: Some(env.block.height - 1)
+        }).unwrap()).unwrap();
+        assert_eq!((total.power, total.height), (Uint128::zero(), env.block.height - 1));
+
+        let info: InfoResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::Info {}).unwrap()).unwrap();
+        assert_eq!((info.info.contract.as_str(), info.info.version.as_str()), (CONTRACT_NAME, CONTRACT_VERSION));
+        let dao: Addr = from_json(query(deps.as_ref(), env.clone(), QueryMsg::Dao { pool_id: 0 }).unwrap()).unwrap();
+        assert_eq!(dao, "dao");
+
+        // a pool without a DAO has none to report
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::SetDao { pool_id: 0, dao: None }).unwrap();
+        assert!(query(deps.as_ref(), env, QueryMsg::Dao { pool_id: 0 }).is_err());
+    }
+
+    #[test]
+    fn members_leave_no_empty_rows() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("bob", 50, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+
+        // settling a non-staker does not create rows for it
+        let err = execute(deps.as_mut(), mock_env(), mock_info("carol", &[]), ExecuteMsg::ClaimReward {
+            pool_id: 0,
+            staker: None,
+            recipient: None
+        }).unwrap_err();
+        assert_eq!(err, ContractError::NoReward {});
+        let carol = (0, Addr::unchecked("carol"));
+        assert!(stakers().may_load(&deps.storage, carol.clone()).unwrap().is_none());
+        assert!(STAKE_AGES.may_load(&deps.storage, carol.clone()).unwrap().is_none());
+        assert!(REWARD_INDEX.may_load(&deps.storage, carol).unwrap().is_none());
+
+        // unstaking everything drops the stake age and the member entry
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), ExecuteMsg::CreateUnstake {
+            pool_id: 0,
+            unstake_amount: Uint128::from(100u128),
+            staker: None
+        }).unwrap();
+        assert!(STAKE_AGES.may_load(&deps.storage, (0, Addr::unchecked("alice"))).unwrap().is_none());
+        let members: MemberListResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::ListMembers {
+            pool_id: 0,
+            start_after: None,
+            limit: Some(1)
+        }).unwrap()).unwrap();
+        assert_eq!(members.members, vec![Member { addr: "bob".to_string(), weight: 50 }]);
+    }
+
+    #[test]
+    fn apy_breakdown() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            apy_prefix: Uint128::from(365u128),
+            price_source: Some(PriceSource::Fixed { price: Decimal::one() }),
+            stake_decimals: Some(0),
+            ..instantiate_msg()
+        }).unwrap();
+
+        // nothing staked earns nothing
+        let apy = query_apy(deps.as_ref(), &mock_env(), 0).unwrap();
+        assert_eq!((apy.apr, apy.apy, apy.total_staked), (Decimal::zero(), Decimal256::zero(), Uint128::zero()));
+        assert_eq!(apy.yearly_emission, Uint128::from(365_000u128));
+
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 1000, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        let apy = query_apy(deps.as_ref(), &mock_env(), 0).unwrap();
+        assert_eq!(apy.total_staked, Uint128::from(1000u128));
+        assert_eq!(apy.stake_price, Decimal::one());
+        assert_eq!(apy.apr, Decimal::permille(365));
+        // compounded daily: 1.001^365 - 1
+        assert!(apy.apy > Decimal256::from_ratio(44025u128, 100_000u128));
+        assert!(apy.apy < Decimal256::from_ratio(44026u128, 100_000u128));
+    }
+
+    #[test]
+    fn position_shows_stake_unbonding_and_share() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 300, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("bob", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::CreateUnstake {
+            pool_id: 0,
+            unstake_amount: Uint128::from(100u128),
+            staker: None
+        }).unwrap();
+        let unlock_time = env.block.time.seconds() + 14 * 86400;
+
+        let res: PositionsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::Positions {
+            pool_id: 0,
+            addresses: vec![Addr::unchecked("alice"), Addr::unchecked("carol")]
+        }).unwrap()).unwrap();
+        let alice = &res.positions[0];
+        assert_eq!(alice.staked, Uint128::from(200u128));
+        assert_eq!(alice.pending_reward, Uint128::from(750u128));
+        assert_eq!(alice.unbonding, vec![UnbondingEntry { index: 0, amount: Uint128::from(100u128), unlock_time, matured: false }]);
+        assert_eq!((alice.matured_amount, alice.next_unlock_time), (Uint128::zero(), Some(unlock_time)));
+        // unbonding gFOT stays in the pool's staked amount until fetched
+        assert_eq!(alice.pool_share, Decimal::percent(50));
+        assert_eq!((alice.stake_since, alice.reward_multiplier), (Some(mock_env().block.time.seconds()), Decimal::one()));
+        // bob's reward is not settled yet and still shows as pending
+        let bob = query_position(deps.as_ref(), &env, 0, Addr::unchecked("bob")).unwrap();
+        assert_eq!(bob.pending_reward, Uint128::from(250u128));
+        let carol = &res.positions[1];
+        assert_eq!((carol.staked, carol.pending_reward, carol.stake_since), (Uint128::zero(), Uint128::zero(), None));
+        assert!(carol.unbonding.is_empty());
+
+        env.block.time = env.block.time.plus_seconds(14 * 86400);
+        let alice = query_position(deps.as_ref(), &env, 0, Addr::unchecked("alice")).unwrap();
+        assert!(alice.unbonding[0].matured);
+        assert_eq!((alice.matured_amount, alice.next_unlock_time), (Uint128::from(100u128), None));
+    }
+
+    #[test]
+    fn top_stakers_by_amount() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::CreatePool {
+            pool: Box::new(instantiate_msg())
+        }).unwrap();
+        for (staker, amount) in [("alice", 5u128), ("bob", 7), ("carol", 5), ("dave", 1), ("erin", 9)] {
+            execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive(staker, amount, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        }
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("frank", 100, &ReceiveMsg::Stake { pool_id: 1 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("dave", &[]), ExecuteMsg::CreateUnstake {
+            pool_id: 0,
+            unstake_amount: Uint128::from(1u128),
+            staker: None
+        }).unwrap();
+
+        // pages of two in descending amount, equal amounts by descending address
+        let mut start_after = None;
+        let mut top = vec![];
+        loop {
+            let res: StakerListResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::TopStakers {
+                pool_id: 0,
+                start_after: start_after.clone(),
+                limit: Some(2)
+            }).unwrap()).unwrap();
+            if res.stakers.is_empty() {
+                break;
+            }
+            start_after = res.stakers.last().map(|staker| staker.address.to_string());
+            top.extend(res.stakers.into_iter().map(|staker| (staker.address.to_string(), staker.amount.u128())));
+        }
+        let expected = [("erin", 9), ("bob", 7), ("carol", 5), ("alice", 5)];
+        assert_eq!(top, expected.iter().map(|(addr, amount)| (addr.to_string(), *amount)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stats_track_pool_activity() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            fee_bps: 1000,
+            treasury: Some("treasury".to_string()),
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("bob", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 5000, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::ClaimReward { pool_id: 0, staker: None, recipient: None }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), ExecuteMsg::CreateUnstake {
+            pool_id: 0,
+            unstake_amount: Uint128::from(100u128),
+            staker: None
+        }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::CollectFees { pool_id: 0 }).unwrap();
+
+        let stats: StatsResponse = from_json(query(deps.as_ref(), env, QueryMsg::Stats { pool_id: 0 }).unwrap()).unwrap();
+        assert_eq!(stats, StatsResponse {
+            staker_count: 1,
+            unbonding_count: 1,
+            fot_claimed: Uint128::from(450u128),
+            fot_funded: Uint128::from(5000u128),
+            sfot_credited: Uint128::zero(),
+            fees_taken: Uint128::from(50u128),
+            fees_collected: Uint128::from(50u128)
+        });
+    }
+
+    #[test]
+    fn rewards_are_pro_rata_and_match_liabilities() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("gfot", &[]), receive("bob", 300, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+
+        let owed = |deps: Deps, env: &Env| {
+            let alice = query_position(deps, env, 0, Addr::unchecked("alice")).unwrap().pending_reward;
+            let bob = query_position(deps, env, 0, Addr::unchecked("bob")).unwrap().pending_reward;
+            let cfg = POOLS.load(deps.storage, 0).unwrap();
+            let state = accrue_global_reward(&cfg, REWARD_STATE.load(deps.storage, 0).unwrap(), env.block.time.seconds()).unwrap();
+            (alice.u128(), bob.u128(), state.liabilities.u128())
+        };
+        // the first day goes to alice alone, the second is shared 1:3
+        env.block.time = env.block.time.plus_seconds(86400);
+        assert_eq!(owed(deps.as_ref(), &env), (1250, 750, 2000));
+
+        // withdrawing the stake leaves the stakers earning on their own weight
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::WithdrawStake { pool_id: 0 }).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        assert_eq!(owed(deps.as_ref(), &env), (1500, 1500, 3000));
+        // as does a redistributed forfeit
+        let mut cfg = POOLS.load(&deps.storage, 0).unwrap();
+        update_global_reward(&mut deps.storage, &env, 0).unwrap();
+        distribute_reward(&mut deps.storage, 0, &mut cfg, Uint128::from(400u128)).unwrap();
+        assert_eq!(owed(deps.as_ref(), &env), (1600, 1800, 3400));
+    }
+
+    #[test]
+    fn ious_settle_in_pages() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        let staker = |i: u32| Addr::unchecked(format!("staker{:02}", i));
+        for i in 0..40 {
+            set_iou(deps.as_mut().storage, 0, staker(i), Uint128::from(100u128)).unwrap();
+        }
+        update_reward_state(deps.as_mut().storage, 0, |state| state.liabilities = Uint128::from(4000u128)).unwrap();
+        let iou = |deps: Deps, i: u32| IOUS.may_load(deps.storage, (0, staker(i))).unwrap().unwrap_or_default().u128();
+
+        // the first page of 30 is half covered
+        let res = execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 1500, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        assert_eq!(res.messages.len(), 30);
+        assert_eq!((iou(deps.as_ref(), 0), iou(deps.as_ref(), 29), iou(deps.as_ref(), 30)), (50, 50, 100));
+        assert_eq!(REWARD_STATE.load(&deps.storage, 0).unwrap().iou_cursor, Some(staker(29)));
+
+        // the next page takes the last 10 and wraps around to the first 20
+        let res = execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 1500, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        assert_eq!(res.messages.len(), 30);
+        assert_eq!((iou(deps.as_ref(), 30), iou(deps.as_ref(), 39)), (25, 25));
+        assert_eq!((iou(deps.as_ref(), 0), iou(deps.as_ref(), 19), iou(deps.as_ref(), 20)), (13, 13, 50));
+        let state = REWARD_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!(state.iou_cursor, Some(staker(19)));
+        assert_eq!(state.liabilities, state.ious);
+        assert_eq!(state.ious, Uint128::from(4000u128 - 1500 - 10 * 75 - 20 * 37));
+    }
+
+    #[test]
+    fn import_batches_apply_once() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        let add_stakers = |batch_id: &str| ExecuteMsg::AddStakers {
+            pool_id: 0,
+            stakers: vec![StakerInfo {
+                address: Addr::unchecked("alice"),
+                amount: Uint128::from(100u128),
+                reward: Uint128::from(10u128),
+                last_time: 0,
+                sfot_reward: Uint128::zero()
+            }],
+            mode: Some(ImportMode::Add),
+            batch_id: batch_id.to_string()
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), add_stakers("a")).unwrap();
+
+        // a retried batch is rejected instead of adding the stakers twice
+        let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), add_stakers("a")).unwrap_err();
+        assert_eq!(err, ContractError::BatchAlreadyImported { batch_id: "a".to_string() });
+        let check = |deps: Deps, amount: u128, reward: u128| {
+            let (staked, owed, _, _) = stakers().load(deps.storage, (0, Addr::unchecked("alice"))).unwrap();
+            assert_eq!((staked.u128(), owed.u128()), (amount, reward));
+            assert_eq!(POOLS.load(deps.storage, 0).unwrap().staked_amount.u128(), amount);
+            assert_eq!(REWARD_STATE.load(deps.storage, 0).unwrap().liabilities.u128(), reward);
+        };
+        check(deps.as_ref(), 100, 10);
+
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), add_stakers("b")).unwrap();
+        check(deps.as_ref(), 200, 20);
+    }
+
+    #[test]
+    fn force_exit_returns_locks_and_positions() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            boost: Some(Boost {
+                bfot_step: Uint128::from(100u128),
+                bfot_boost: Decimal::percent(10),
+                collections: vec![crate::state::BoostCollection { address: Addr::unchecked("nftcol"), boost: Decimal::percent(25) }],
+                cap: Decimal::percent(200)
+            }),
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 5000, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("bfot", &[]), receive("alice", 100, &ReceiveMsg::Boost { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("nftcol", &[]), ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            sender: "alice".to_string(),
+            token_id: "7".to_string(),
+            msg: to_json_binary(&ReceiveMsg::Boost { pool_id: 0 }).unwrap()
+        })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::SetPositionNft {
+            pool_id: 0,
+            address: Some("nft".to_string())
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("bob", 200, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+
+        // alice weighs 135 of 335
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::RemoveStaker {
+            pool_id: 0,
+            address: Addr::unchecked("alice")
+        }).unwrap();
+        assert_eq!(res.messages.len(), 4);
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "gfot"), ("alice".to_string(), Uint128::from(100u128)));
+        assert_eq!(cw20_transfer(&res.messages[1].msg, "fot"), ("alice".to_string(), Uint128::from(402u128)));
+        assert_eq!(cw20_transfer(&res.messages[2].msg, "bfot"), ("alice".to_string(), Uint128::from(100u128)));
+        match &res.messages[3].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) => {
+                assert_eq!(contract_addr, "nftcol");
+                assert_eq!(from_json::<Cw721ExecuteMsg>(msg).unwrap(), Cw721ExecuteMsg::TransferNft {
+                    recipient: "alice".to_string(),
+                    token_id: "7".to_string()
+                });
+            },
+            other => panic!("unexpected message {:?}", other)
+        }
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().staked_amount, Uint128::from(200u128));
+        assert!(BOOST_LOCKS.may_load(&deps.storage, (0, Addr::unchecked("alice"))).unwrap().is_none());
+
+        // the purge reaches bob through his position
+        let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::RemoveAllStakers {
+            pool_id: 0,
+            start_after: None,
+            limit: None
+        }).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "gfot"), ("bob".to_string(), Uint128::from(200u128)));
+        assert_eq!(cw20_transfer(&res.messages[1].msg, "fot"), ("bob".to_string(), Uint128::from(597u128)));
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().staked_amount, Uint128::zero());
+        assert!(positions().may_load(&deps.storage, 1).unwrap().is_none());
+        let state = REWARD_STATE.load(&deps.storage, 0).unwrap();
+        // what the rounding kept from either of them stays in the liabilities
+        assert_eq!((state.total_weight, state.bonus_weight, state.liabilities), (Uint128::zero(), Uint128::zero(), Uint128::one()));
+        env.block.height += 1;
+        let total = query_total_staked_at_height(deps.as_ref(), env, 0, None).unwrap();
+        assert_eq!(total.total, Uint128::zero());
+    }
+
+    fn sfot_tree(a: &str, b: &str) -> (String, String, String) {
+        let (a, b): ([u8; 32], [u8; 32]) = (sha2::Sha256::digest(a.as_bytes()).into(), sha2::Sha256::digest(b.as_bytes()).into());
+        let mut leaves = [a, b];
+        leaves.sort_unstable();
+        let root: [u8; 32] = sha2::Sha256::digest(&leaves.concat()).into();
+        // the proof of each leaf is the other one
+        (hex::encode(root), hex::encode(b), hex::encode(a))
+    }
+
+    fn sfot_balance(balance: u128) -> cosmwasm_std::QuerierResult {
+        SystemResult::Ok(ContractResult::Ok(to_json_binary(&cw20::BalanceResponse { balance: Uint128::from(balance) }).unwrap()))
+    }
+
+    #[test]
+    fn sfot_stages_claim_with_proofs() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        let register = |merkle_root: &str, total_amount: u128| ExecuteMsg::RegisterSfotStage {
+            merkle_root: merkle_root.to_string(),
+            sfot_token_address: "sfot".to_string(),
+            total_amount: Uint128::from(total_amount),
+            start: None,
+            expiration: None
+        };
+        let claim = |stage: u8, amount: u128, proof: &str| ExecuteMsg::ClaimSfot {
+            stage,
+            amount: Uint128::from(amount),
+            proof: vec![proof.to_string()]
+        };
+
+        // the stages only go out for less than they add up to
+        let (root, alice_proof, bob_proof) = sfot_tree("alice100", "bob50");
+        deps.querier.update_wasm(|_| sfot_balance(100));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), register(&root, 120)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughsFOT { needed: Uint128::from(120u128) });
+        deps.querier.update_wasm(|_| sfot_balance(300));
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), register(&root, 120)).unwrap();
+        let (other_root, _, _) = sfot_tree("alice7", "bob9");
+        let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), register(&other_root, 200)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughsFOT { needed: Uint128::from(320u128) });
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), register(&other_root, 180)).unwrap();
+
+        // claims are not held up by a disabled pool
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::UpdateConstants {
+            pool_id: 0,
+            daily_fot_amount: Uint128::from(1000u128),
+            apy_prefix: Uint128::zero(),
+            reward_interval: 86400,
+            delta_time: 0,
+            lock_days: 14,
+            enabled: false
+        }).unwrap();
+
+        let mut tampered = hex::decode(&alice_proof).unwrap();
+        tampered[0] ^= 1;
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), claim(1, 100, &hex::encode(tampered))).unwrap_err();
+        assert_eq!(err, ContractError::VerificationFailed {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), claim(1, 101, &alice_proof)).unwrap_err();
+        assert_eq!(err, ContractError::VerificationFailed {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), claim(2, 100, &alice_proof)).unwrap_err();
+        assert_eq!(err, ContractError::VerificationFailed {});
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), claim(1, 100, &alice_proof)).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "sfot"), ("alice".to_string(), Uint128::from(100u128)));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), claim(1, 100, &alice_proof)).unwrap_err();
+        assert_eq!(err, ContractError::Claimed {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), claim(1, 50, &bob_proof)).unwrap_err();
+        assert_eq!(err, ContractError::StageExceeded { stage: 1 });
+
+        let totals: SfotTotalsResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::SfotTotals {}).unwrap()).unwrap();
+        assert_eq!((totals.registered, totals.claimed), (Uint128::from(300u128), Uint128::from(100u128)));
+        assert_eq!(STATS.may_load(&deps.storage, 0).unwrap().unwrap_or_default().sfot_credited, Uint128::zero());
+    }
+
+
+    /// The single pool contract as it was before pools: CONFIG and the legacy staker rows
+    fn legacy_deps(stakers: &[(&str, u128)], staked_amount: u128) -> cosmwasm_std::OwnedDeps<cosmwasm_std::MemoryStorage, cosmwasm_std::testing::MockApi, cosmwasm_std::testing::MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        let mut cfg = POOLS.load(&deps.storage, 0).unwrap();
+        cfg.staked_amount = Uint128::from(staked_amount);
+        cfg.fot_amount = Uint128::from(10_000u128);
+        POOLS.remove(&mut deps.storage, 0);
+        REWARD_STATE.remove(&mut deps.storage, 0);
+        ADMIN.remove(&mut deps.storage);
+        POOL_COUNT.remove(&mut deps.storage);
+        CONFIG.save(&mut deps.storage, &cfg).unwrap();
+        let now = mock_env().block.time.seconds();
+        for (address, amount) in stakers {
+            let row = (Uint128::from(*amount), Uint128::zero(), now, Uint128::zero());
+            LEGACY_STAKERS.save(&mut deps.storage, Addr::unchecked(*address), &row).unwrap();
+        }
+        deps
+    }
+
+    #[test]
+    fn migrate_stakers_in_batches() {
+        let mut deps = legacy_deps(&[("alice", 200), ("bob", 200), ("carol", 200), ("dave", 200)], 1000);
+        let start = mock_env().block.time.seconds();
+        LEGACY_UNSTAKING.save(&mut deps.storage, Addr::unchecked("alice"), &vec![(Uint128::from(10u128), start)]).unwrap();
+        LEGACY_UNSTAKING.save(&mut deps.storage, Addr::unchecked("erin"), &vec![(Uint128::from(20u128), start)]).unwrap();
+        positions().save(&mut deps.storage, 1, &Position {
+            pool_id: 0,
+            amount: Uint128::from(200u128),
+            lock_days: 14,
+            start_time: start,
+            reward: Uint128::zero(),
+            last_time: start,
+            reward_index: Decimal256::zero(),
+            owner: None
+        }).unwrap();
+
+        // migrate only sets up the pool, the legacy rows stay where they are
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().staked_amount, Uint128::from(1000u128));
+        assert_eq!(ADMIN.load(&deps.storage).unwrap(), Some(Addr::unchecked("owner")));
+        assert_eq!(REWARD_STATE.load(&deps.storage, 0).unwrap().total_weight, Uint128::zero());
+        assert_eq!(LEGACY_STAKERS.keys(&deps.storage, None, None, Order::Ascending).count(), 4);
+
+        // a day later the owner moves two at a time: stakers, then unstaking lists, then positions
+        env.block.time = env.block.time.plus_seconds(86400);
+        let batch = ExecuteMsg::MigrateStakers { limit: Some(2) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), batch.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let done = |res: Response| res.attributes.iter().find(|a| a.key == "done").unwrap().value.clone();
+        for _ in 0..3 {
+            let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), batch.clone()).unwrap();
+            assert_eq!(done(res), "false");
+        }
+        assert_eq!(UNSTAKING.load(&deps.storage, (0, Addr::unchecked("erin"))).unwrap().len(), 1);
+        assert_eq!(LEGACY_MIGRATION.load(&deps.storage).unwrap().last_position, Some(1));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), batch).unwrap();
+        assert_eq!(done(res), "true");
+        assert!(CONFIG.may_load(&deps.storage).unwrap().is_none());
+        assert!(LEGACY_MIGRATION.may_load(&deps.storage).unwrap().is_none());
+
+        // each earned 200 before the migration and 200 since, the weight and liabilities add up
+        let state = REWARD_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!(state.total_weight, Uint128::from(1000u128));
+        assert_eq!(state.liabilities, Uint128::from(2000u128));
+        let alice = query_position(deps.as_ref(), &env, 0, Addr::unchecked("alice")).unwrap();
+        assert_eq!((alice.pending_reward, alice.unbonding.len()), (Uint128::from(400u128), 1));
+        let position = positions().load(&deps.storage, 1).unwrap();
+        assert_eq!((position.reward, position.last_time), (Uint128::from(400u128), start + 2 * 86400));
+        assert_eq!(query_stats(deps.as_ref(), 0).unwrap().staker_count, 4);
+        // the single pool contract kept no stake ages, they start at the migration
+        assert_eq!(STAKE_AGES.load(&deps.storage, (0, Addr::unchecked("alice"))).unwrap().since, start + 86400);
+    }
+
+    #[test]
+    fn legacy_stakers_move_on_first_touch() {
+        let mut deps = legacy_deps(&[("alice", 500), ("bob", 500)], 1000);
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+        // alice claims her share from before and after the migration, bob is left as he was
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::ClaimReward {
+            pool_id: 0,
+            staker: None,
+            recipient: None
+        }).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "fot"), ("alice".to_string(), Uint128::from(1000u128)));
+        assert!(LEGACY_STAKERS.may_load(&deps.storage, Addr::unchecked("alice")).unwrap().is_none());
+        assert!(LEGACY_STAKERS.may_load(&deps.storage, Addr::unchecked("bob")).unwrap().is_some());
+        let state = REWARD_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!((state.total_weight, state.liabilities), (Uint128::from(500u128), Uint128::zero()));
+
+        // bob's share of the day waits in the index until he is moved
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::MigrateStakers { limit: None }).unwrap();
+        let state = REWARD_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!((state.total_weight, state.liabilities), (Uint128::from(1000u128), Uint128::from(1000u128)));
+        let bob = query_position(deps.as_ref(), &env, 0, Addr::unchecked("bob")).unwrap();
+        assert_eq!(bob.pending_reward, Uint128::from(1000u128));
+    }
+
+    #[test]
+    fn pools_are_separate() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        let pool = InstantiateMsg {
+            stake_token: AssetInfo::Token { contract_addr: "lptoken".to_string() },
+            daily_fot_amount: Uint128::from(500u128),
+            ..instantiate_msg()
+        };
+        let create = ExecuteMsg::CreatePool { pool: Box::new(pool) };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), create).unwrap();
+
+        // each pool only takes its own stake token
+        let err = execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 1 }));
+        assert!(err.is_err());
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("lptoken", &[]), receive("bob", 250, &ReceiveMsg::Stake { pool_id: 1 })).unwrap();
+
+        // and emits to its own stakers
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let pending = |pool_id: u64, address: &str| {
+            query_position(deps.as_ref(), &env, pool_id, Addr::unchecked(address)).unwrap().pending_reward.u128()
+        };
+        assert_eq!((pending(0, "alice"), pending(1, "alice")), (1000, 0));
+        assert_eq!((pending(0, "bob"), pending(1, "bob")), (0, 500));
+        assert_eq!(POOLS.load(&deps.storage, 0).unwrap().staked_amount, Uint128::from(100u128));
+        assert_eq!(POOLS.load(&deps.storage, 1).unwrap().staked_amount, Uint128::from(250u128));
+        assert_eq!(REWARD_STATE.load(&deps.storage, 1).unwrap().total_weight, Uint128::from(250u128));
+    }
+
+
+    #[test]
+    fn native_pools_take_and_pay_coins() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            stake_token: AssetInfo::NativeToken { denom: "ujuno".to_string() },
+            reward_denom: Some("uatom".to_string()),
+            ..instantiate_msg()
+        }).unwrap();
+        let stake = ExecuteMsg::Stake { pool_id: 0 };
+
+        // exactly one coin of the stake denom
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(100, "uatom")), stake.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MissingDenom("ujuno".to_string())));
+        let funds = vec![coin(100, "ujuno"), coin(100, "uatom")];
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &funds), stake.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MultipleDenoms {}));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), stake.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(0, "ujuno")), stake.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+        // and neither the stake nor the reward comes in as a cw20
+        let err = execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 100, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap_err();
+        assert_eq!(err, ContractError::UnacceptableToken {});
+
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(100, "ujuno")), stake).unwrap();
+        let fund = ExecuteMsg::FundRewards { pool_id: 0 };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &coins(5000, "ujuno")), fund.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MissingDenom("uatom".to_string())));
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &coins(5000, "uatom")), fund).unwrap();
+
+        // the reward and the unstaked coins go out with bank sends
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::ClaimReward {
+            pool_id: 0,
+            staker: None,
+            recipient: None
+        }).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "alice".to_string(),
+            amount: coins(1000, "uatom")
+        }));
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::CreateUnstake {
+            pool_id: 0,
+            unstake_amount: Uint128::from(100u128),
+            staker: None
+        }).unwrap();
+        env.block.time = env.block.time.plus_seconds(14 * 86400);
+        let res = execute(deps.as_mut(), env, mock_info("alice", &[]), ExecuteMsg::FetchUnstake {
+            pool_id: 0,
+            index: 0,
+            staker: None,
+            recipient: None
+        }).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "alice".to_string(),
+            amount: coins(100, "ujuno")
+        }));
+    }
+
+
+    #[test]
+    fn emitted_between_follows_schedule() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg()).unwrap();
+        let mut cfg = POOLS.load(&deps.storage, 0).unwrap();
+        let day = |d: u64| d * 86400;
+        let emitted = |cfg: &Config, from: u64, to: u64| emitted_between(cfg, from, to).unwrap().u128();
+
+        cfg.emission = Some(EmissionSchedule::Segments { segments: vec![
+            EmissionSegment { start_time: day(100), rate: Uint128::from(10u128) },
+            EmissionSegment { start_time: day(103), rate: Uint128::from(20u128) },
+        ] });
+        // daily_fot_amount until the first segment
+        assert_eq!(emitted(&cfg, day(98), day(101)), 2 * 1000 + 10);
+        // crossing into the next segment
+        assert_eq!(emitted(&cfg, day(101), day(105)), 2 * 10 + 2 * 20);
+        // starting mid-interval, the interval ending on the boundary is still at the old rate
+        assert_eq!(emitted(&cfg, day(101) + 43200, day(104) + 43200), 2 * 10 + 20);
+
+        cfg.emission = Some(EmissionSchedule::Decay {
+            start_time: day(100),
+            initial_rate: Uint128::from(1000u128),
+            period: day(2),
+            factor: Decimal::percent(50)
+        });
+        // crossing two decay periods
+        assert_eq!(emitted(&cfg, day(101), day(105)), 1000 + 2 * 500 + 250);
+        // starting mid-period, halfway through an interval
+        assert_eq!(emitted(&cfg, day(103) + 43200, day(106)), 500 + 2 * 250);
+        assert_eq!(emission_rate(&cfg, day(103)).unwrap(), Uint128::from(500u128));
+        // nothing emitted within a single interval
+        assert_eq!(emitted(&cfg, day(104) + 1, day(105) - 1), 0);
+    }
+
+
+    #[test]
+    fn loyalty_follows_stake_age() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            loyalty: Some(Loyalty {
+                curve: LoyaltyCurve::Linear { per_day: Decimal::percent(10) },
+                cap: Decimal::percent(150),
+                unstake_reset: Decimal::percent(80)
+            }),
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("bob", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("fot", &[]), receive("owner", 50_000, &ReceiveMsg::InitialFund { pool_id: 0 })).unwrap();
+        let start = mock_env().block.time.seconds();
+
+        // 1, 1.1, 1.2, 1.3 and 1.4 for a day each, then capped at 1.5
+        let cfg = POOLS.load(&deps.storage, 0).unwrap();
+        assert_eq!(average_loyalty(&cfg, start, start, start + 10 * 86400), Decimal::percent(135));
+        assert_eq!(average_loyalty(&cfg, start, start + 5 * 86400, start + 10 * 86400), Decimal::percent(150));
+
+        // stakers that were never settled show the multiplier of their age
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(10 * 86400);
+        let bob = query_staker(deps.as_ref(), &env, 0, Addr::unchecked("bob")).unwrap();
+        assert_eq!((bob.stake_since, bob.reward_multiplier), (Some(start), Decimal::percent(150)));
+        let position = query_position(deps.as_ref(), &env, 0, Addr::unchecked("bob")).unwrap();
+        assert_eq!((position.reward_multiplier, position.pending_reward), (Decimal::percent(150), Uint128::from(6750u128)));
+
+        // and earn at the average of the period once settled, the bonus owed on top
+        let claim = |staker: &str| ExecuteMsg::ClaimReward { pool_id: 0, staker: None, recipient: Some(staker.to_string()) };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), claim("bob")).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "fot"), ("bob".to_string(), Uint128::from(6750u128)));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), claim("alice")).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0].msg, "fot"), ("alice".to_string(), Uint128::from(6750u128)));
+        let state = REWARD_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!((state.liabilities, state.bonus_weight), (Uint128::zero(), Uint128::from(100u128)));
+
+        // unstaking takes 80% of the age away
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::CreateUnstake {
+            pool_id: 0,
+            unstake_amount: Uint128::from(50u128),
+            staker: None
+        }).unwrap();
+        let alice = query_staker(deps.as_ref(), &env, 0, Addr::unchecked("alice")).unwrap();
+        assert_eq!((alice.stake_since, alice.reward_multiplier), (Some(start + 8 * 86400), Decimal::percent(120)));
+        assert_eq!(REWARD_STATE.load(&deps.storage, 0).unwrap().bonus_weight, Uint128::from(60u128));
+    }
+
+
+    #[test]
+    fn boost_locks_stay_within_cap() {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg {
+            boost: Some(Boost {
+                bfot_step: Uint128::from(100u128),
+                bfot_boost: Decimal::percent(10),
+                collections: vec![crate::state::BoostCollection { address: Addr::unchecked("nftcol"), boost: Decimal::percent(25) }],
+                cap: Decimal::percent(130)
+            }),
+            ..instantiate_msg()
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gfot", &[]), receive("alice", 100, &ReceiveMsg::Stake { pool_id: 0 })).unwrap();
+        let lock = |amount: u128| receive("alice", amount, &ReceiveMsg::Boost { pool_id: 0 });
+        let nft = |token_id: &str| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            sender: "alice".to_string(),
+            token_id: token_id.to_string(),
+            msg: to_json_binary(&ReceiveMsg::Boost { pool_id: 0 }).unwrap()
+        });
+        let locked = |deps: Deps| BOOST_LOCKS.may_load(deps.storage, (0, Addr::unchecked("alice"))).unwrap().unwrap_or_default();
+
+        // a lock past the cap is rejected whole, the lock stays as it was
+        let err = execute(deps.as_mut(), mock_env(), mock_info("bfot", &[]), lock(400)).unwrap_err();
+        assert_eq!(err, ContractError::BoostCapped {});
+        assert_eq!(locked(deps.as_ref()), BoostLock::default());
+        execute(deps.as_mut(), mock_env(), mock_info("bfot", &[]), lock(200)).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("nftcol", &[]), nft("7")).unwrap_err();
+        assert_eq!(err, ContractError::BoostCapped {});
+        assert!(locked(deps.as_ref()).nfts.is_empty());
+
+        // up to the cap exactly is fine
+        execute(deps.as_mut(), mock_env(), mock_info("bfot", &[]), lock(100)).unwrap();
+        assert_eq!(locked(deps.as_ref()).bfot, Uint128::from(300u128));
+        let alice = query_staker(deps.as_ref(), &mock_env(), 0, Addr::unchecked("alice")).unwrap();
+        assert_eq!(alice.reward_multiplier, Decimal::percent(130));
+        assert_eq!(REWARD_STATE.load(&deps.storage, 0).unwrap().bonus_weight, Uint128::from(30u128));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("bfot", &[]), lock(100)).unwrap_err();
+        assert_eq!(err, ContractError::BoostCapped {});
+    }
+}
@@ -1,5 +1,5 @@
 use cosmwasm_std::{StdError, Uint128};
-use cw_utils::{Expiration, Scheduled};
+use cw_utils::{Expiration, PaymentError, Scheduled};
 use hex::FromHexError;
 use thiserror::Error;
 
@@ -11,12 +11,51 @@ pub enum ContractError {
     #[error("{0}")]
     Hex(#[from] FromHexError),
 
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("Disabled")]
     Disabled {},
 
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Operator approval expired")]
+    Expired {},
+
+    #[error("Stake transfers are disabled")]
+    TransferDisabled {},
+
+    #[error("Position NFT is not configured")]
+    NoPositionNft {},
+
+    #[error("Boost is not configured")]
+    NoBoost {},
+
+    #[error("Lock would take the boost past its cap")]
+    BoostCapped {},
+
+    #[error("Not locked for boost")]
+    NotLocked {},
+
+    #[error("Treasury is not configured")]
+    NoTreasury {},
+
+    #[error("Given address already registered as a hook")]
+    HookAlreadyRegistered {},
+
+    #[error("Given address not registered as a hook")]
+    HookNotRegistered {},
+
+    #[error("Staker import is finalized")]
+    ImportFinalized {},
+
+    #[error("Batch {batch_id} already imported")]
+    BatchAlreadyImported { batch_id: String },
+
+    #[error("Duplicate staker {address} in batch")]
+    DuplicateStaker { address: String },
+
     #[error("Still Locked")]
     StillLocked {},
 
@@ -30,7 +69,7 @@ pub enum ContractError {
     #[error("Still in Lock period")]
     StillInLock { },
 
-    #[error("Not FOT or gFOT token")]
+    #[error("Token not accepted by the pool")]
     UnacceptableToken {},
 
     #[error("Not enough gFOT")]
@@ -51,6 +90,18 @@ pub enum ContractError {
     #[error("Already claimed")]
     Claimed {},
 
+    #[error("Merkle proof verification failed")]
+    VerificationFailed {},
+
+    #[error("Claim exceeds the total of stage {stage}")]
+    StageExceeded { stage: u8 },
+
+    #[error("Not enough sFOT, needs {needed}")]
+    NotEnoughsFOT { needed: Uint128 },
+
+    #[error("Airdrop stage {stage} not expired yet")]
+    StageNotExpired { stage: u8 },
+
     #[error("Wrong length")]
     WrongLength {},
 
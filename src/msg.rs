@@ -1,20 +1,61 @@
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use cw20::{Cw20ReceiveMsg};
-use cosmwasm_std::{Uint128, Addr};
+use cosmwasm_std::{Uint128, Addr, Binary, Empty, Decimal, Decimal256, Order};
+use cw_utils::{Expiration, Scheduled};
+use cw2::ContractVersion;
+use crate::state::{Boost, EmissionSchedule, EmissionSegment, LockedNft, Loyalty, PriceSource, Term, Vesting, VestingEntry};
 
-#[derive(Serialize, Deserialize, JsonSchema)]
+/// Creates pool 0. CreatePool takes the same fields for further pools.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     /// Owner if none set to info.sender.
     pub owner: Option<String>,
     pub fot_token_address: Addr,
     pub bfot_token_address: Addr,
-    pub gfot_token_address: Addr,
+    /// Staked asset, any cw20 including LP tokens. Also read from `gfot_token_address`
+    /// as a plain cw20 address.
+    #[serde(alias = "gfot_token_address", deserialize_with = "deserialize_stake_token")]
+    pub stake_token: AssetInfo,
+    /// Native denom rewards are funded and paid in instead of the FOT token
+    #[serde(default)]
+    pub reward_denom: Option<String>,
     pub daily_fot_amount: Uint128,
     pub apy_prefix: Uint128,
     pub delta_time: u64,
     pub reward_interval: u64,
-    pub lock_days: u64
+    pub lock_days: u64,
+    #[serde(default)]
+    pub transfer_enabled: bool,
+    /// DAO core contract if this contract is its voting module
+    #[serde(default)]
+    pub dao: Option<String>,
+    /// Bonding curve if not set
+    #[serde(default)]
+    pub price_source: Option<PriceSource>,
+    /// Decimals of the stake token, those of gFOT (10) if not set
+    #[serde(default)]
+    pub stake_decimals: Option<u8>,
+    /// Makes the pool take term deposits
+    #[serde(default)]
+    pub term: Option<Term>,
+    /// Emission over time, daily_fot_amount if not set
+    #[serde(default)]
+    pub emission: Option<EmissionSchedule>,
+    /// Makes claimed rewards vest
+    #[serde(default)]
+    pub vesting: Option<Vesting>,
+    /// Makes reward weight grow with stake age
+    #[serde(default)]
+    pub loyalty: Option<Loyalty>,
+    /// Lets stakers lock bFOT and NFTs for a reward boost
+    #[serde(default)]
+    pub boost: Option<Boost>,
+    /// Protocol fee on reward payouts, in basis points
+    #[serde(default)]
+    pub fee_bps: u64,
+    #[serde(default)]
+    pub treasury: Option<String>
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -32,15 +73,28 @@ pub struct SfotRewardInfo {
     pub sfot_reward: Uint128
 }
 
+/// Messages with a `pool_id` act on that pool, pool 0 if not set.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    /// Registers a new pool with its own tokens, constants and totals, admin only
+    CreatePool {
+        pool: Box<InstantiateMsg>
+    },
+    /// Sets the admin allowed to create pools and run sFOT stages. None locks both.
+    UpdateAdmin {
+        new_admin: Option<String>
+    },
     UpdateConfig {
+        #[serde(default)]
+        pool_id: u64,
         /// NewOwner if non sent, contract gets locked. Recipients can receive airdrops
         /// but owner cannot register new stages.
         new_owner: Option<String>,
     },
     UpdateConstants {
+        #[serde(default)]
+        pool_id: u64,
         daily_fot_amount: Uint128,
         apy_prefix: Uint128,
         reward_interval: u64,
@@ -49,52 +103,481 @@ pub enum ExecuteMsg {
         enabled: bool
     },
     Receive(Cw20ReceiveMsg),
-    WithdrawFot { },
-    WithdrawGFot { },
-    ClaimReward { },
+    /// Stakes the funds sent in the native stake denom of the pool
+    Stake {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// Adds the funds sent in the reward denom of the pool to its reward pool
+    FundRewards {
+        #[serde(default)]
+        pool_id: u64
+    },
+    WithdrawFot {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// Withdraws the staked tokens held by the pool, owner only
+    #[serde(alias = "withdraw_g_fot")]
+    WithdrawStake {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// Claims the pending FOT reward of `staker` (sender if not set), paid to `recipient`
+    /// (the staker if not set). Acting on another staker requires an operator approval.
+    /// In vesting mode the reward vests for the staker and the recipient is picked by WithdrawVested.
+    ClaimReward {
+        #[serde(default)]
+        pool_id: u64,
+        staker: Option<String>,
+        recipient: Option<String>
+    },
     CreateUnstake {
-        unstake_amount: Uint128
+        #[serde(default)]
+        pool_id: u64,
+        unstake_amount: Uint128,
+        staker: Option<String>
     },
     FetchUnstake {
-        index: u64
+        #[serde(default)]
+        pool_id: u64,
+        index: u64,
+        staker: Option<String>,
+        recipient: Option<String>
+    },
+    /// Allows `operator` to claim and unstake on behalf of the sender until `expires`
+    /// (never if not set).
+    ApproveOperator {
+        operator: String,
+        expires: Option<Expiration>
+    },
+    RevokeOperator {
+        operator: String
+    },
+    /// Moves `amount` of the sender's staked gFOT to `recipient` without unbonding.
+    /// With `include_reward` the sender's pending FOT reward moves along.
+    TransferStake {
+        #[serde(default)]
+        pool_id: u64,
+        recipient: String,
+        amount: Uint128,
+        include_reward: Option<bool>
+    },
+    SetTransferEnabled {
+        #[serde(default)]
+        pool_id: u64,
+        enabled: bool
+    },
+    /// Pay underfunded claims pro-rata and record the remainder as an IOU
+    SetUnderfundedPayouts {
+        #[serde(default)]
+        pool_id: u64,
+        enabled: bool
+    },
+    /// Sets the cw721 contract stakes are minted into. The contract must be its minter.
+    /// None switches back to plain STAKERS accounting for new stakes.
+    SetPositionNft {
+        #[serde(default)]
+        pool_id: u64,
+        address: Option<String>
     },
+    /// Claims the pending FOT reward of a position, allowed for the NFT owner or its operators.
+    /// In vesting mode the reward vests for the owner. Also moves the position's staked balance
+    /// to the owner, with or without a reward to claim.
+    ClaimPositionReward {
+        #[serde(default)]
+        pool_id: u64,
+        token_id: String,
+        recipient: Option<String>
+    },
+    SetDao {
+        #[serde(default)]
+        pool_id: u64,
+        dao: Option<String>
+    },
+    SetPriceSource {
+        #[serde(default)]
+        pool_id: u64,
+        price_source: PriceSource
+    },
+    /// Registers a contract receiving MemberChangedHookMsg on stake changes
+    AddHook {
+        #[serde(default)]
+        pool_id: u64,
+        addr: String
+    },
+    RemoveHook {
+        #[serde(default)]
+        pool_id: u64,
+        addr: String
+    },
+    /// Imports stakers. Addresses must be valid and unique within the batch,
+    /// a batch id can only be imported once.
     AddStakers {
-        stakers: Vec<StakerInfo>
+        #[serde(default)]
+        pool_id: u64,
+        stakers: Vec<StakerInfo>,
+        /// Defaults to overwrite
+        mode: Option<ImportMode>,
+        /// Unique per batch, so a retried batch that already landed is rejected
+        batch_id: String
+    },
+    /// Permanently closes AddStakers
+    FinalizeImport {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// Moves up to `limit` stakers, unstaking lists and positions of the single pool contract
+    /// into pool 0, resuming where the previous call stopped. Until moved they are moved when
+    /// they are first touched and not counted in pool 0 queries and snapshots.
+    MigrateStakers {
+        limit: Option<u32>
     },
+    /// Same as ForceExit, kept for existing scripts
     RemoveStaker {
+        #[serde(default)]
+        pool_id: u64,
         address: Addr
     },
+    /// Same as PurgeStakers, kept for existing scripts
     RemoveAllStakers {
+        #[serde(default)]
+        pool_id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>
+    },
+    /// Settles and pays out the reward of `address`, returns its staked gFOT, unbonding entries,
+    /// positions, deposits and boosting bFOT and NFTs regardless of their lock and removes it.
+    /// Deposits pay the part of their reward accrued so far. Payouts are charged the protocol fee.
+    /// Positions are paid to the address they count for and their NFTs no longer hold a position.
+    ForceExit {
+        #[serde(default)]
+        pool_id: u64,
+        address: String
+    },
+    /// ForceExit for a page of stakers, then of depositors, position owners and boost lockers
+    /// once the stakers are exhausted. The response data is a PurgeStakersResponse with the cursor to continue from.
+    PurgeStakers {
+        #[serde(default)]
+        pool_id: u64,
         start_after: Option<String>,
         limit: Option<u32>
     },
     AddSfotRewards {
+        #[serde(default)]
+        pool_id: u64,
         rewards: Vec<SfotRewardInfo>
+    },
+    /// Registers a new sFOT distribution stage. The contract must hold `total_amount`
+    /// of the sFOT token on top of what open stages of the token still owe.
+    RegisterSfotStage {
+        /// Hex encoded sha256 merkle root of sha256(address + amount) leaves
+        merkle_root: String,
+        sfot_token_address: String,
+        total_amount: Uint128,
+        start: Option<Scheduled>,
+        expiration: Option<Expiration>
+    },
+    /// Claims the sender's sFOT of a stage with the hex encoded merkle proof of its leaf
+    ClaimSfot {
+        stage: u8,
+        amount: Uint128,
+        proof: Vec<String>
+    },
+    /// Returns the unclaimed sFOT of an expired stage to the owner
+    ReclaimSfot {
+        stage: u8
+    },
+    /// Sets the term of new deposits. None switches the pool back to open-ended staking,
+    /// open deposits keep their term.
+    SetTerm {
+        #[serde(default)]
+        pool_id: u64,
+        term: Option<Term>
+    },
+    /// Pays a matured deposit of the sender back with its reward, also while the pool is disabled
+    Redeem {
+        #[serde(default)]
+        pool_id: u64,
+        id: u64
+    },
+    /// Replaces the emission schedule, rewards up to now accrue at the previous one.
+    /// None emits daily_fot_amount.
+    SetEmissionSchedule {
+        #[serde(default)]
+        pool_id: u64,
+        schedule: Option<EmissionSchedule>
+    },
+    /// Sets the vesting of future claims. None pays claims instantly, vesting claims keep
+    /// their terms.
+    SetVesting {
+        #[serde(default)]
+        pool_id: u64,
+        vesting: Option<Vesting>
+    },
+    /// Releases the vested rewards of the sender to `recipient` (the sender if not set).
+    /// With `early` the unvested rest, less the protocol fee, is forfeited to the stakers and
+    /// the sender's vesting ends. Also while the pool is disabled.
+    WithdrawVested {
+        #[serde(default)]
+        pool_id: u64,
+        #[serde(default)]
+        early: bool,
+        #[serde(default)]
+        recipient: Option<String>
+    },
+    /// Sets the loyalty curve. Multipliers follow it as stakers are settled.
+    SetLoyalty {
+        #[serde(default)]
+        pool_id: u64,
+        loyalty: Option<Loyalty>
+    },
+    /// Sets the boost of locked bFOT and NFTs. Multipliers follow it as stakers are settled.
+    SetBoost {
+        #[serde(default)]
+        pool_id: u64,
+        boost: Option<Boost>
+    },
+    /// NFT of a boost collection sent with a ReceiveMsg::Boost, locked for its owner,
+    /// or a position NFT sent with a ReceiveMsg::UnstakePosition
+    ReceiveNft(Cw721ReceiveMsg),
+    /// Returns locked bFOT to the sender
+    UnlockBfot {
+        #[serde(default)]
+        pool_id: u64,
+        amount: Uint128
+    },
+    /// Returns a locked NFT to the sender
+    UnlockNft {
+        #[serde(default)]
+        pool_id: u64,
+        collection: String,
+        token_id: String
+    },
+    /// Sets the protocol fee taken from reward payouts and the treasury it is collected to
+    SetFee {
+        #[serde(default)]
+        pool_id: u64,
+        fee_bps: u64,
+        treasury: Option<String>
+    },
+    /// Sends the fees taken so far to the treasury
+    CollectFees {
+        #[serde(default)]
+        pool_id: u64
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ReceiveMsg {
-    Stake {},
-    InitialFund {},
+    Stake {
+        #[serde(default)]
+        pool_id: u64
+    },
+    InitialFund {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// Locks the bFOT or NFT sent for a reward boost, rejected if it would take the boost past the cap
+    Boost {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// Burns the position NFT sent and moves its gFOT into the sender's unstaking list
+    /// and its pending reward into the sender's staker reward
+    UnstakePosition {
+        #[serde(default)]
+        pool_id: u64
+    },
 }
+/// Queries with a `pool_id` read that pool, pool 0 if not set.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    Config {},
+    Admin {},
+    ListPools {
+        start_after: Option<u64>,
+        limit: Option<u32>
+    },
+    Config {
+        #[serde(default)]
+        pool_id: u64
+    },
     Staker {
+        #[serde(default)]
+        pool_id: u64,
         address: Addr
     },
     ListStakers {
+        #[serde(default)]
+        pool_id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        /// Address order, ascending if not set
+        order: Option<OrderBy>
+    },
+    /// Stakers by staked amount, largest first. `start_after` is the last address of the previous page.
+    TopStakers {
+        #[serde(default)]
+        pool_id: u64,
         start_after: Option<String>,
         limit: Option<u32>
     },
     Apy {
-
+        #[serde(default)]
+        pool_id: u64
     },
     Unstaking {
+        #[serde(default)]
+        pool_id: u64,
         address: Addr
+    },
+    Operator {
+        staker: Addr,
+        operator: Addr
+    },
+    NftPosition {
+        token_id: String
+    },
+    ListNftPositions {
+        start_after: Option<String>,
+        limit: Option<u32>
+    },
+    /// Staked gFOT of `address` at the start of block `height` (current if not set),
+    /// including the positions it holds as of its last stake or claim. Transferring a position
+    /// NFT does not notify the contract, so the position counts for the previous holder until
+    /// the new one calls ClaimPositionReward.
+    StakedBalanceAtHeight {
+        #[serde(default)]
+        pool_id: u64,
+        address: Addr,
+        height: Option<u64>
+    },
+    TotalStakedAtHeight {
+        #[serde(default)]
+        pool_id: u64,
+        height: Option<u64>
+    },
+    /// DAO DAO voting module interface, voting power is staked gFOT
+    VotingPowerAtHeight {
+        #[serde(default)]
+        pool_id: u64,
+        address: String,
+        height: Option<u64>
+    },
+    TotalPowerAtHeight {
+        #[serde(default)]
+        pool_id: u64,
+        height: Option<u64>
+    },
+    Info {},
+    Dao {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// cw4 group interface, member weight is staked gFOT
+    Member {
+        #[serde(default)]
+        pool_id: u64,
+        addr: String,
+        at_height: Option<u64>
+    },
+    ListMembers {
+        #[serde(default)]
+        pool_id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>
+    },
+    TotalWeight {
+        #[serde(default)]
+        pool_id: u64
+    },
+    Hooks {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// Staked amount, unbonding entries and pending rewards of `address` in one response
+    Position {
+        #[serde(default)]
+        pool_id: u64,
+        address: Addr
+    },
+    Positions {
+        #[serde(default)]
+        pool_id: u64,
+        addresses: Vec<Addr>
+    },
+    Stats {
+        #[serde(default)]
+        pool_id: u64
+    },
+    SfotStage {
+        stage: u8
+    },
+    LatestSfotStage {},
+    SfotClaimed {
+        stage: u8,
+        address: String
+    },
+    /// sFOT registered, claimed and reclaimed over all stages
+    SfotTotals {},
+    /// Unclaimed FOT owed to stakers against the FOT funded for rewards
+    Solvency {
+        #[serde(default)]
+        pool_id: u64
+    },
+    /// The emission schedule with its segments starting after `start_after`,
+    /// decay periods listed as segments
+    EmissionSchedule {
+        #[serde(default)]
+        pool_id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>
+    },
+    /// Rewards of `address` vesting in the pool
+    Vesting {
+        #[serde(default)]
+        pool_id: u64,
+        address: String
+    },
+    /// bFOT and NFTs `address` locked in the pool and their boost
+    Boost {
+        #[serde(default)]
+        pool_id: u64,
+        address: String
+    },
+    /// Term deposits of `address` across pools
+    Deposits {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Adds the imported amounts and rewards to existing entries
+    Add,
+    /// Replaces existing entries with the imported ones
+    #[default]
+    Overwrite
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderBy {
+    Asc,
+    Desc
+}
+
+impl From<OrderBy> for Order {
+    fn from(order: OrderBy) -> Order {
+        match order {
+            OrderBy::Asc => Order::Ascending,
+            OrderBy::Desc => Order::Descending
+        }
     }
 }
 
@@ -104,15 +587,34 @@ pub struct ConfigResponse {
     pub owner: Option<String>,
     pub fot_token_address: String,
     pub bfot_token_address: String,
-    pub gfot_token_address: String,
+    pub stake_token: AssetInfo,
+    pub reward_denom: Option<String>,
     pub fot_amount: Uint128,
-    pub gfot_amount: Uint128,
+    pub staked_amount: Uint128,
     pub daily_fot_amount: Uint128,
     pub apy_prefix: Uint128,
     pub delta_time: u64,
     pub reward_interval: u64,
-    pub lock_days: u64
-
+    pub lock_days: u64,
+    pub transfer_enabled: bool,
+    pub position_nft: Option<String>,
+    pub dao: Option<String>,
+    pub price_source: PriceSource,
+    pub stake_decimals: u8,
+    pub underfunded_payouts: bool,
+    pub import_finalized: bool,
+    pub term: Option<Term>,
+    pub reserved_amount: Uint128,
+    pub deposited_amount: Uint128,
+    pub emission: Option<EmissionSchedule>,
+    pub vesting: Option<Vesting>,
+    pub vesting_amount: Uint128,
+    pub loyalty: Option<Loyalty>,
+    pub boost: Option<Boost>,
+    pub fee_bps: u64,
+    pub treasury: Option<String>,
+    /// Fees taken and not collected yet
+    pub fee_amount: Uint128
 }
 
 
@@ -134,9 +636,385 @@ pub struct StakerResponse {
     pub amount: Uint128,
     pub reward: Uint128,
     pub last_time: u64,
-    pub sfot_reward: Uint128
+    pub sfot_reward: Uint128,
+    /// FOT still owed from partially paid claims
+    pub iou: Uint128,
+    /// Start of the uninterrupted stake, None if not staking
+    pub stake_since: Option<u64>,
+    /// Reward weight per staked token from the loyalty of the stake age and boost, at the queried time
+    pub reward_multiplier: Decimal
 }
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct CountInfo {
     pub count: u128
 }
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct OperatorResponse {
+    pub staker: Addr,
+    pub operator: Addr,
+    pub approved: bool,
+    pub expires: Expiration
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct NftPositionResponse {
+    pub token_id: String,
+    pub amount: Uint128,
+    pub lock_days: u64,
+    pub start_time: u64,
+    pub reward: Uint128,
+    pub last_time: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct EmissionScheduleResponse {
+    pub schedule: Option<EmissionSchedule>,
+    /// Emitted per reward interval now
+    pub current_rate: Uint128,
+    pub segments: Vec<EmissionSegment>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VestingResponse {
+    pub address: Addr,
+    /// Claimed into vesting and not withdrawn yet
+    pub amount: Uint128,
+    /// Part of amount WithdrawVested releases now
+    pub vested: Uint128,
+    pub entries: Vec<VestingEntry>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct BoostResponse {
+    pub address: Addr,
+    pub bfot: Uint128,
+    pub nfts: Vec<LockedNft>,
+    /// Boost of the locks under the current boost config
+    pub boost_multiplier: Decimal
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DepositResponse {
+    pub id: u64,
+    pub pool_id: u64,
+    pub amount: Uint128,
+    pub reward: Uint128,
+    pub start_time: u64,
+    pub maturity: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DepositListResponse {
+    pub deposits: Vec<DepositResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct NftPositionListResponse {
+    pub positions: Vec<NftPositionResponse>,
+}
+
+/// Subset of the cw721-base execute messages used to mint and burn positions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721ExecuteMsg {
+    Mint(Cw721MintMsg),
+    Burn {
+        token_id: String
+    },
+    TransferNft {
+        recipient: String,
+        token_id: String
+    }
+}
+
+/// cw721 Send hook, as cw721-base calls it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721ReceiveMsg {
+    pub sender: String,
+    pub token_id: String,
+    pub msg: Binary
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721MintMsg {
+    pub token_id: String,
+    pub owner: String,
+    pub token_uri: Option<String>,
+    pub extension: Option<Empty>
+}
+
+/// Subset of the cw721-base query messages
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721QueryMsg {
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Cw721OwnerOfResponse {
+    pub owner: String
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct StakedBalanceAtHeightResponse {
+    pub balance: Uint128,
+    pub height: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TotalStakedAtHeightResponse {
+    pub total: Uint128,
+    pub height: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VotingPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TotalPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct InfoResponse {
+    pub info: ContractVersion
+}
+
+/// cw4 compatible member, weight is staked gFOT
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Member {
+    pub addr: String,
+    pub weight: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MemberResponse {
+    pub weight: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MemberListResponse {
+    pub members: Vec<Member>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TotalWeightResponse {
+    pub weight: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct HooksResponse {
+    pub hooks: Vec<String>
+}
+
+/// Weight change of a member, None meaning not a member
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MemberDiff {
+    pub key: String,
+    pub old: Option<u64>,
+    pub new: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MemberChangedHookMsg {
+    pub diffs: Vec<MemberDiff>
+}
+
+/// Execute message sent to every registered hook
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberChangedExecuteMsg {
+    MemberChangedHook(MemberChangedHookMsg)
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ApyResponse {
+    /// Simple yearly rate, 0.5 meaning 50%
+    pub apr: Decimal,
+    /// `apr` compounded once per reward interval
+    pub apy: Decimal256,
+    /// FOT emitted to stakers over a year at the current emission rate
+    pub yearly_emission: Uint128,
+    pub total_staked: Uint128,
+    /// Price of one staked token in the unit of apy_prefix, used to value the stake
+    pub stake_price: Decimal
+}
+
+/// Asset of a Junoswap/Terraswap style pair
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    Token {
+        contract_addr: String
+    },
+    NativeToken {
+        denom: String
+    }
+}
+
+impl AssetInfo {
+    /// Whether this is the cw20 token at `address`
+    pub fn is_token(&self, address: &Addr) -> bool {
+        matches!(self, AssetInfo::Token { contract_addr } if contract_addr == address.as_str())
+    }
+}
+
+/// Reads an AssetInfo, or the cw20 address a gFOT specific field held before
+pub fn deserialize_stake_token<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AssetInfo, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StakeToken {
+        Asset(AssetInfo),
+        Address(String)
+    }
+
+    Ok(match StakeToken::deserialize(deserializer)? {
+        StakeToken::Asset(info) => info,
+        StakeToken::Address(contract_addr) => AssetInfo::Token { contract_addr }
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Asset {
+    pub info: AssetInfo,
+    pub amount: Uint128
+}
+
+/// Subset of the pair contract queries used for pricing
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PairQueryMsg {
+    Pool {},
+    Simulation {
+        offer_asset: Asset
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PoolResponse {
+    pub assets: [Asset; 2],
+    pub total_share: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SimulationResponse {
+    pub return_amount: Uint128,
+    pub spread_amount: Uint128,
+    pub commission_amount: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct UnbondingEntry {
+    /// Index to pass to FetchUnstake
+    pub index: u64,
+    pub amount: Uint128,
+    pub unlock_time: u64,
+    pub matured: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PositionResponse {
+    pub address: Addr,
+    pub staked: Uint128,
+    pub unbonding: Vec<UnbondingEntry>,
+    /// Unbonded gFOT ready to be fetched
+    pub matured_amount: Uint128,
+    /// FOT reward including accrual not yet settled into the staker
+    pub pending_reward: Uint128,
+    pub pending_sfot_reward: Uint128,
+    pub next_unlock_time: Option<u64>,
+    /// Share of the rewards emitted per interval
+    pub pool_share: Decimal,
+    pub stake_since: Option<u64>,
+    /// Reward weight per staked token from the loyalty of the stake age and boost, at the queried time
+    pub reward_multiplier: Decimal
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PositionsResponse {
+    pub positions: Vec<PositionResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct StatsResponse {
+    pub staker_count: u64,
+    pub unbonding_count: u64,
+    pub fot_claimed: Uint128,
+    pub fot_funded: Uint128,
+    /// sFOT credited with AddSfotRewards, stage claims are in SfotTotals
+    pub sfot_credited: Uint128,
+    pub fees_taken: Uint128,
+    pub fees_collected: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SolvencyResponse {
+    /// FOT accrued to stakers and positions and not claimed yet
+    pub liabilities: Uint128,
+    /// FOT held for rewards (fot_amount)
+    pub funded: Uint128,
+    pub surplus: Uint128,
+    pub deficit: Uint128,
+    /// Days the surplus lasts at the current emission rate, None if nothing is emitted
+    pub runway_days: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PurgeStakersResponse {
+    pub purged: u32,
+    /// start_after for the next batch, None once all stakers and depositors are purged
+    pub next_start_after: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SfotStageResponse {
+    pub stage: u8,
+    pub merkle_root: String,
+    pub sfot_token_address: String,
+    pub total_amount: Uint128,
+    pub claimed_amount: Uint128,
+    pub start: Option<Scheduled>,
+    pub expiration: Expiration,
+    pub reclaimed: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct LatestSfotStageResponse {
+    pub latest_stage: u8
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SfotClaimedResponse {
+    pub claimed: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SfotTotalsResponse {
+    pub registered: Uint128,
+    pub claimed: Uint128,
+    pub reclaimed: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct AdminResponse {
+    pub admin: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PoolConfigResponse {
+    pub pool_id: u64,
+    pub config: ConfigResponse
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PoolListResponse {
+    pub pools: Vec<PoolConfigResponse>
+}
@@ -0,0 +1,203 @@
+use cosmwasm_std::{Addr, Empty, Uint128, to_json_binary};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+
+use crate::msg::{
+    AssetInfo, Cw721OwnerOfResponse, Cw721QueryMsg, ExecuteMsg, InstantiateMsg, NftPositionResponse, QueryMsg, ReceiveMsg,
+    StakedBalanceAtHeightResponse, TotalStakedAtHeightResponse
+};
+
+const OWNER: &str = "owner";
+const ALICE: &str = "alice";
+const BOB: &str = "bob";
+const DAY: u64 = 86400;
+
+fn staking_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        crate::contract::execute,
+        crate::contract::instantiate,
+        crate::contract::query,
+    ))
+}
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+fn cw721_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw721_base::entry::execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    ))
+}
+
+fn instantiate_cw20(app: &mut App, code_id: u64, symbol: &str, holder: &str, amount: u128) -> Addr {
+    let msg = cw20_base::msg::InstantiateMsg {
+        name: symbol.to_string(),
+        symbol: symbol.to_string(),
+        decimals: 6,
+        initial_balances: vec![Cw20Coin { address: holder.to_string(), amount: Uint128::from(amount) }],
+        mint: None,
+        marketing: None,
+    };
+    app.instantiate_contract(code_id, Addr::unchecked(OWNER), &msg, &[], symbol, None).unwrap()
+}
+
+fn staked_balance(app: &App, staking: &Addr, address: &str) -> (Uint128, Uint128) {
+    let balance: StakedBalanceAtHeightResponse = app.wrap()
+        .query_wasm_smart(staking, &QueryMsg::StakedBalanceAtHeight { pool_id: 0, address: Addr::unchecked(address), height: None })
+        .unwrap();
+    let total: TotalStakedAtHeightResponse = app.wrap()
+        .query_wasm_smart(staking, &QueryMsg::TotalStakedAtHeight { pool_id: 0, height: None })
+        .unwrap();
+    (balance.balance, total.total)
+}
+
+fn cw20_balance(app: &App, token: &Addr, address: &str) -> Uint128 {
+    let res: BalanceResponse = app.wrap()
+        .query_wasm_smart(token, &Cw20QueryMsg::Balance { address: address.to_string() })
+        .unwrap();
+    res.balance
+}
+
+/// FOT, gFOT, the staking contract and its position NFT, alice holding 500 gFOT
+fn setup_positions(app: &mut App) -> (Addr, Addr, Addr, Addr) {
+    let cw20_id = app.store_code(cw20_contract());
+    let cw721_id = app.store_code(cw721_contract());
+    let staking_id = app.store_code(staking_contract());
+
+    let fot = instantiate_cw20(app, cw20_id, "FOT", OWNER, 10_000);
+    let gfot = instantiate_cw20(app, cw20_id, "GFOT", ALICE, 500);
+    let staking = app.instantiate_contract(staking_id, Addr::unchecked(OWNER), &InstantiateMsg {
+        owner: None,
+        fot_token_address: fot.clone(),
+        bfot_token_address: Addr::unchecked("bfot"),
+        stake_token: AssetInfo::Token { contract_addr: gfot.to_string() },
+        reward_denom: None,
+        daily_fot_amount: Uint128::from(1000u128),
+        apy_prefix: Uint128::zero(),
+        delta_time: 0,
+        reward_interval: DAY,
+        lock_days: 1,
+        transfer_enabled: false,
+        dao: None,
+        price_source: None,
+        stake_decimals: None,
+        term: None,
+        emission: None,
+        vesting: None,
+        loyalty: None,
+        boost: None,
+        fee_bps: 0,
+        treasury: None
+    }, &[], "staking", None).unwrap();
+    let nft = app.instantiate_contract(cw721_id, Addr::unchecked(OWNER), &cw721_base::InstantiateMsg {
+        name: "gFOT position".to_string(),
+        symbol: "GFOTP".to_string(),
+        minter: staking.to_string(),
+    }, &[], "positions", None).unwrap();
+
+    app.execute_contract(Addr::unchecked(OWNER), staking.clone(), &ExecuteMsg::SetPositionNft {
+        pool_id: 0,
+        address: Some(nft.to_string())
+    }, &[]).unwrap();
+    app.execute_contract(Addr::unchecked(OWNER), fot.clone(), &Cw20ExecuteMsg::Send {
+        contract: staking.to_string(),
+        amount: Uint128::from(10_000u128),
+        msg: to_json_binary(&ReceiveMsg::InitialFund { pool_id: 0 }).unwrap()
+    }, &[]).unwrap();
+
+    (fot, gfot, staking, nft)
+}
+
+#[test]
+fn position_stake_claim_unstake() {
+    let mut app = App::default();
+    let (fot, gfot, staking, nft) = setup_positions(&mut app);
+
+    // stake mints position 1 to alice
+    app.execute_contract(Addr::unchecked(ALICE), gfot.clone(), &Cw20ExecuteMsg::Send {
+        contract: staking.to_string(),
+        amount: Uint128::from(500u128),
+        msg: to_json_binary(&ReceiveMsg::Stake { pool_id: 0 }).unwrap()
+    }, &[]).unwrap();
+    let owner_of = Cw721QueryMsg::OwnerOf { token_id: "1".to_string(), include_expired: None };
+    let owner: Cw721OwnerOfResponse = app.wrap().query_wasm_smart(&nft, &owner_of).unwrap();
+    assert_eq!(owner.owner, ALICE);
+    let position: NftPositionResponse = app.wrap()
+        .query_wasm_smart(&staking, &QueryMsg::NftPosition { token_id: "1".to_string() })
+        .unwrap();
+    assert_eq!(position.amount, Uint128::from(500u128));
+    // the position counts as staked for its owner
+    app.update_block(|block| block.height += 1);
+    assert_eq!(staked_balance(&app, &staking, ALICE), (Uint128::from(500u128), Uint128::from(500u128)));
+
+    // two intervals of emission go to the only position
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * DAY));
+    app.execute_contract(Addr::unchecked(ALICE), staking.clone(), &ExecuteMsg::ClaimPositionReward {
+        pool_id: 0,
+        token_id: "1".to_string(),
+        recipient: None
+    }, &[]).unwrap();
+    assert_eq!(cw20_balance(&app, &fot, ALICE), Uint128::from(2000u128));
+
+    // unstaking sends the NFT in, the contract burns it once it holds it
+    app.execute_contract(Addr::unchecked(ALICE), nft.clone(), &cw721_base::ExecuteMsg::<Option<Empty>, Empty>::SendNft {
+        contract: staking.to_string(),
+        token_id: "1".to_string(),
+        msg: to_json_binary(&ReceiveMsg::UnstakePosition { pool_id: 0 }).unwrap()
+    }, &[]).unwrap();
+    assert!(app.wrap().query_wasm_smart::<Cw721OwnerOfResponse>(&nft, &owner_of).is_err());
+    let unstaking: Vec<(Uint128, u64)> = app.wrap()
+        .query_wasm_smart(&staking, &QueryMsg::Unstaking { pool_id: 0, address: Addr::unchecked(ALICE) })
+        .unwrap();
+    assert_eq!(unstaking.len(), 1);
+    assert_eq!(unstaking[0].0, Uint128::from(500u128));
+    app.update_block(|block| block.height += 1);
+    assert_eq!(staked_balance(&app, &staking, ALICE), (Uint128::zero(), Uint128::zero()));
+
+    // the gFOT comes back once the lock ends
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY));
+    app.execute_contract(Addr::unchecked(ALICE), staking, &ExecuteMsg::FetchUnstake {
+        pool_id: 0,
+        index: 0,
+        staker: None,
+        recipient: None
+    }, &[]).unwrap();
+    assert_eq!(cw20_balance(&app, &gfot, ALICE), Uint128::from(500u128));
+}
+
+#[test]
+fn position_balance_moves_on_claim() {
+    let mut app = App::default();
+    let (_, gfot, staking, nft) = setup_positions(&mut app);
+    app.execute_contract(Addr::unchecked(ALICE), gfot, &Cw20ExecuteMsg::Send {
+        contract: staking.to_string(),
+        amount: Uint128::from(500u128),
+        msg: to_json_binary(&ReceiveMsg::Stake { pool_id: 0 }).unwrap()
+    }, &[]).unwrap();
+
+    // the transfer is not seen by the staking contract
+    app.execute_contract(Addr::unchecked(ALICE), nft, &cw721_base::ExecuteMsg::<Option<Empty>, Empty>::TransferNft {
+        recipient: BOB.to_string(),
+        token_id: "1".to_string()
+    }, &[]).unwrap();
+    app.update_block(|block| block.height += 1);
+    assert_eq!(staked_balance(&app, &staking, ALICE), (Uint128::from(500u128), Uint128::from(500u128)));
+    assert_eq!(staked_balance(&app, &staking, BOB), (Uint128::zero(), Uint128::from(500u128)));
+
+    // claiming without a reward moves the balance to the new holder
+    app.execute_contract(Addr::unchecked(BOB), staking.clone(), &ExecuteMsg::ClaimPositionReward {
+        pool_id: 0,
+        token_id: "1".to_string(),
+        recipient: None
+    }, &[]).unwrap();
+    app.update_block(|block| block.height += 1);
+    assert_eq!(staked_balance(&app, &staking, ALICE), (Uint128::zero(), Uint128::from(500u128)));
+    assert_eq!(staked_balance(&app, &staking, BOB), (Uint128::from(500u128), Uint128::from(500u128)));
+}
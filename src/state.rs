@@ -1,8 +1,10 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Decimal, Decimal256, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotMap, Strategy};
+use cw_utils::{Expiration, Scheduled};
+use crate::msg::{AssetInfo, deserialize_stake_token};
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -11,23 +13,506 @@ pub struct Config {
     pub owner: Option<Addr>,
     pub fot_token_address: Addr,
     pub bfot_token_address: Addr,
-    pub gfot_token_address: Addr,
+    /// Staked asset, saved as `gfot_token_address` before it was generic
+    #[serde(alias = "gfot_token_address", deserialize_with = "deserialize_stake_token")]
+    pub stake_token: AssetInfo,
+    /// Native denom rewards are paid in. If None set, rewards are paid in the FOT token.
+    #[serde(default)]
+    pub reward_denom: Option<String>,
     pub fot_amount: Uint128,
-    pub gfot_amount: Uint128,
+    /// Total staked, saved as `gfot_amount` before the stake token was generic
+    #[serde(alias = "gfot_amount")]
+    pub staked_amount: Uint128,
     pub daily_fot_amount: Uint128,
     pub apy_prefix: Uint128,
     pub delta_time: u64,
     pub reward_interval: u64,
     pub lock_days: u64,
-    pub enabled: bool
-    
+    pub enabled: bool,
+    /// Whether stakers may move staked gFOT to another address with TransferStake
+    #[serde(default)]
+    pub transfer_enabled: bool,
+    /// cw721 contract (minted by this contract) representing stakes as positions.
+    /// If None set, stakes are credited to STAKERS.
+    #[serde(default)]
+    pub position_nft: Option<Addr>,
+    /// DAO core contract using this contract as its voting module
+    #[serde(default)]
+    pub dao: Option<Addr>,
+    #[serde(default)]
+    pub price_source: PriceSource,
+    /// Decimals of the stake token, those of gFOT for configs saved before it was set
+    #[serde(default = "default_stake_decimals")]
+    pub stake_decimals: u8,
+    /// When the reward pool cannot cover all liabilities, claims pay their pro-rata share
+    /// of it and the remainder is recorded in IOUS instead of failing with NotEnoughFOT
+    #[serde(default)]
+    pub underfunded_payouts: bool,
+    /// Set by FinalizeImport, AddStakers is rejected afterwards
+    #[serde(default)]
+    pub import_finalized: bool,
+    /// Term deposit mode. If set, stakes become deposits locked for the term
+    /// instead of earning the daily emission.
+    #[serde(default)]
+    pub term: Option<Term>,
+    /// Rewards of open deposits, taken out of fot_amount when deposited
+    #[serde(default)]
+    pub reserved_amount: Uint128,
+    /// Principal of open deposits, not part of staked_amount
+    #[serde(default)]
+    pub deposited_amount: Uint128,
+    /// Emission per reward interval over time. daily_fot_amount is emitted until it starts
+    /// and when not set.
+    #[serde(default)]
+    pub emission: Option<EmissionSchedule>,
+    /// Vesting mode. If set, claimed rewards vest linearly and are released by WithdrawVested.
+    #[serde(default)]
+    pub vesting: Option<Vesting>,
+    /// Claimed rewards vesting and not withdrawn yet, taken out of fot_amount when claimed
+    #[serde(default)]
+    pub vesting_amount: Uint128,
+    /// Reward weight multiplier of stakers by the age of their stake. If None set,
+    /// every staked gFOT weighs the same.
+    #[serde(default)]
+    pub loyalty: Option<Loyalty>,
+    /// Reward weight boost from bFOT and NFTs locked by stakers. If None set, nothing
+    /// can be locked.
+    #[serde(default)]
+    pub boost: Option<Boost>,
+    /// Protocol fee on reward payouts, in basis points
+    #[serde(default)]
+    pub fee_bps: u64,
+    /// Receives the fees swept by CollectFees
+    #[serde(default)]
+    pub treasury: Option<Addr>,
+    /// Fees taken and not collected yet, not part of fot_amount
+    #[serde(default)]
+    pub fee_amount: Uint128
+}
+
+pub const GFOT_DECIMALS: u8 = 10;
+
+fn default_stake_decimals() -> u8 {
+    GFOT_DECIMALS
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionSegment {
+    pub start_time: u64,
+    /// Emitted per reward interval from start_time on
+    pub rate: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmissionSchedule {
+    /// Rates from each start time until the next segment, start times ascending
+    Segments {
+        segments: Vec<EmissionSegment>
+    },
+    /// `initial_rate` from start_time, multiplied by `factor` every `period` seconds.
+    /// A factor of 0.5 halves the emission every period. The period is at least the
+    /// reward interval and the rate must decay to zero within 1000 periods.
+    Decay {
+        start_time: u64,
+        initial_rate: Uint128,
+        period: u64,
+        factor: Decimal
+    }
+}
+
+/// Fixed term of a deposit and its reward, `rate` times the deposit paid at maturity
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Term {
+    pub days: u64,
+    pub rate: Decimal
+}
+
+/// Vesting of claimed rewards: nothing before `cliff` seconds, all after `duration` seconds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Vesting {
+    pub duration: u64,
+    pub cliff: u64
+}
+
+/// Multiplier of a staker's reward weight growing with its uninterrupted stake age
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Loyalty {
+    pub curve: LoyaltyCurve,
+    /// Highest multiplier, at least one
+    pub cap: Decimal,
+    /// Share of the stake age CreateUnstake takes away, one resets it
+    pub unstake_reset: Decimal
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LoyaltyCurve {
+    /// One plus `per_day` for every full day staked
+    Linear {
+        per_day: Decimal
+    },
+    /// Multiplier of the last step reached, one before the first, days ascending
+    Steps {
+        steps: Vec<LoyaltyStep>
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LoyaltyStep {
+    pub days: u64,
+    pub multiplier: Decimal
+}
+
+/// Multiplier of a staker's reward weight from bFOT and NFTs it locked, applied on top of loyalty
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Boost {
+    /// bFOT locked per `bfot_boost` added
+    pub bfot_step: Uint128,
+    pub bfot_boost: Decimal,
+    /// cw721 collections whose NFTs can be locked
+    pub collections: Vec<BoostCollection>,
+    /// Highest boost multiplier of a staker, at least one
+    pub cap: Decimal
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BoostCollection {
+    pub address: Addr,
+    /// Added by every NFT of the collection locked
+    pub boost: Decimal
+}
+
+/// Where query_apy takes the stake token price from. Prices are per whole token in the unit
+/// apy_prefix values the yearly emission in (bFOT for the bonding curve).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// gFOT minting cost: 10000 + gFOT supply / 10^10 bFOT
+    BondingCurve {},
+    Fixed {
+        price: Decimal
+    },
+    /// Spot price from the reserves returned by a Junoswap/Terraswap style pair's Pool query.
+    /// `decimals` are the decimals of the pair's other asset.
+    PairPool {
+        contract_addr: Addr,
+        decimals: u8
+    },
+    /// Return of selling one gFOT in the pair's Simulation query, spread and commission included
+    PairSimulation {
+        contract_addr: Addr,
+        decimals: u8
+    }
+}
+
+impl Default for PriceSource {
+    fn default() -> Self {
+        PriceSource::BondingCurve {}
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Position {
+    #[serde(default)]
+    pub pool_id: u64,
+    pub amount: Uint128,
+    pub lock_days: u64,
+    pub start_time: u64,
+    pub reward: Uint128,
+    pub last_time: u64,
+    /// RewardState.reward_per_token the reward was last settled at
+    #[serde(default)]
+    pub reward_index: Decimal256,
+    /// Address the amount counts for in the staked balance snapshots. Set at mint and
+    /// moved to the NFT owner when it claims, None for positions from before snapshots.
+    #[serde(default)]
+    pub owner: Option<Addr>
 }
 
 pub const CONFIG_KEY: &str = "config";
+/// Config of the single pool contract, copied to pool 0 by migrate. Kept as it was until all
+/// legacy stakers are moved, their rewards accrue by it up to the migration.
 pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
 
-pub const STAKERS_KEY: &str = "stakers";
-pub const STAKERS: Map<Addr, (Uint128, Uint128, u64, Uint128)> = Map::new(STAKERS_KEY);
+/// Progress of moving the stakers of the single pool contract into pool 0
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyMigration {
+    /// Time of the migration, the legacy per staker accrual runs up to it
+    pub time: u64,
+    /// Last position MigrateStakers went through
+    pub last_position: Option<u64>
+}
+
+pub const LEGACY_MIGRATION_KEY: &str = "legacy_migration";
+/// Present until MigrateStakers has moved every legacy staker, unstaking list and position
+pub const LEGACY_MIGRATION: Item<LegacyMigration> = Item::new(LEGACY_MIGRATION_KEY);
+
+pub const ADMIN_KEY: &str = "admin";
+/// Creates pools and runs sFOT stages. If None set, both are locked.
+pub const ADMIN: Item<Option<Addr>> = Item::new(ADMIN_KEY);
+
+pub const POOL_COUNT_KEY: &str = "pool_count";
+pub const POOL_COUNT: Item<u64> = Item::new(POOL_COUNT_KEY);
+
+pub const POOLS_KEY: &str = "pools";
+pub const POOLS: Map<u64, Config> = Map::new(POOLS_KEY);
+
+pub const STAKERS_KEY: &str = "pool_stakers";
+
+/// (amount, reward, last_time, sfot_reward)
+pub type StakerData = (Uint128, Uint128, u64, Uint128);
+
+pub struct StakerIndexes<'a> {
+    /// (pool_id, big endian staked amount), ordering the stakers of a pool by amount
+    pub amount: MultiIndex<'a, (u64, Vec<u8>), StakerData, (u64, Addr)>,
+}
+
+impl<'a> IndexList<StakerData> for StakerIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<StakerData>> + '_> {
+        let v: Vec<&dyn Index<StakerData>> = vec![&self.amount];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn amount_index_key(amount: Uint128) -> Vec<u8> {
+    amount.u128().to_be_bytes().to_vec()
+}
+
+/// Pool id of a stakers primary key, which starts with the length prefixed big endian u64
+fn staker_pool_id(pk: &[u8]) -> u64 {
+    let mut pool_id = [0u8; 8];
+    pool_id.copy_from_slice(&pk[2..10]);
+    u64::from_be_bytes(pool_id)
+}
+
+/// (pool_id, address) -> staker
+pub fn stakers<'a>() -> IndexedMap<'a, (u64, Addr), StakerData, StakerIndexes<'a>> {
+    let indexes = StakerIndexes {
+        amount: MultiIndex::new(
+            |pk: &[u8], d: &StakerData| (staker_pool_id(pk), amount_index_key(d.0)),
+            STAKERS_KEY,
+            "pool_stakers__amount"
+        ),
+    };
+    IndexedMap::new(STAKERS_KEY, indexes)
+}
+
+pub const UNSTAKING_KEY: &str = "pool_unstaking";
+pub const UNSTAKING: Map<(u64, Addr), Vec<(Uint128, u64)>> = Map::new(UNSTAKING_KEY);
+
+/// Stakers and unstaking lists of the single pool contract, moved to pool 0 when first
+/// touched or by MigrateStakers
+pub const LEGACY_STAKERS: Map<Addr, StakerData> = Map::new("stakers");
+pub const LEGACY_UNSTAKING: Map<Addr, Vec<(Uint128, u64)>> = Map::new("unstaking");
+
+pub const OPERATORS_KEY: &str = "operators";
+/// (staker, operator) -> expiration of the operator approval
+pub const OPERATORS: Map<(Addr, Addr), Expiration> = Map::new(OPERATORS_KEY);
+
+pub const POSITION_COUNT_KEY: &str = "position_count";
+pub const POSITION_COUNT: Item<u64> = Item::new(POSITION_COUNT_KEY);
+
+pub const POSITIONS_KEY: &str = "positions";
+
+pub struct PositionIndexes<'a> {
+    /// (pool_id, owner), the positions counted for an address; positions without an owner index under ""
+    pub owner: MultiIndex<'a, (u64, Addr), Position, u64>,
+}
+
+impl<'a> IndexList<Position> for PositionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Position>> + '_> {
+        let v: Vec<&dyn Index<Position>> = vec![&self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Position id -> position NFT
+pub fn positions<'a>() -> IndexedMap<'a, u64, Position, PositionIndexes<'a>> {
+    let indexes = PositionIndexes {
+        owner: MultiIndex::new(
+            |_pk: &[u8], p: &Position| (p.pool_id, p.owner.clone().unwrap_or_else(|| Addr::unchecked(""))),
+            POSITIONS_KEY,
+            "positions__owner"
+        ),
+    };
+    IndexedMap::new(POSITIONS_KEY, indexes)
+}
+
+pub const POSITION_STAKES_KEY: &str = "pool_position_stakes";
+/// (pool_id, owner) -> sum of the positions counted for owner in STAKED_BALANCES
+pub const POSITION_STAKES: Map<(u64, Addr), Uint128> = Map::new(POSITION_STAKES_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Deposit {
+    pub pool_id: u64,
+    pub amount: Uint128,
+    /// Reserved at deposit and paid with the principal by Redeem
+    pub reward: Uint128,
+    pub start_time: u64,
+    pub maturity: u64
+}
+
+pub const DEPOSIT_COUNT_KEY: &str = "deposit_count";
+pub const DEPOSIT_COUNT: Item<u64> = Item::new(DEPOSIT_COUNT_KEY);
+
+pub const DEPOSITS_KEY: &str = "deposits";
+/// (owner, deposit id) -> deposit, ids are unique across pools
+pub const DEPOSITS: Map<(Addr, u64), Deposit> = Map::new(DEPOSITS_KEY);
+
+/// Reward claimed in vesting mode, with the vesting terms of its claim
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingEntry {
+    pub amount: Uint128,
+    pub withdrawn: Uint128,
+    pub start_time: u64,
+    pub cliff_time: u64,
+    pub end_time: u64
+}
+
+pub const VESTING_KEY: &str = "vesting";
+/// (pool_id, staker) -> vesting claims, oldest first
+pub const VESTING: Map<(u64, Addr), Vec<VestingEntry>> = Map::new(VESTING_KEY);
+
+/// Staked gFOT per (pool_id, staker), the STAKERS amount plus POSITION_STAKES, checkpointed every
+/// block for voting power queries
+pub const STAKED_BALANCES: SnapshotMap<(u64, Addr), Uint128> = SnapshotMap::new(
+    "pool_staked_balances",
+    "pool_staked_balances__checkpoints",
+    "pool_staked_balances__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Sum of STAKED_BALANCES per pool, checkpointed every block
+pub const STAKED_TOTAL: SnapshotMap<u64, Uint128> = SnapshotMap::new(
+    "pool_staked_total",
+    "pool_staked_total__checkpoints",
+    "pool_staked_total__changelog",
+    Strategy::EveryBlock,
+);
+
+pub const HOOKS_KEY: &str = "pool_hooks";
+/// Contracts notified with a MemberChangedHookMsg when a staked amount of the pool changes
+pub const HOOKS: Map<u64, Vec<Addr>> = Map::new(HOOKS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct Stats {
+    /// Stakers with a non-zero staked amount
+    pub staker_count: u64,
+    /// Entries waiting in UNSTAKING lists
+    pub unbonding_count: u64,
+    pub fot_claimed: Uint128,
+    pub fot_funded: Uint128,
+    /// sFOT credited to stakers with AddSfotRewards
+    pub sfot_credited: Uint128,
+    /// Protocol fees taken from reward payouts
+    #[serde(default)]
+    pub fees_taken: Uint128,
+    /// Protocol fees sent to the treasury
+    #[serde(default)]
+    pub fees_collected: Uint128
+}
+
+pub const STATS_KEY: &str = "pool_stats";
+pub const STATS: Map<u64, Stats> = Map::new(STATS_KEY);
+
+/// Reward accounting of a pool. Emission accrues to reward_per_token per unit of weight,
+/// stakers and positions settle against it with the index they were last paid at.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct RewardState {
+    /// FOT accrued per unit of the pool's reward weight
+    pub reward_per_token: Decimal256,
+    pub last_update: u64,
+    /// Stake earning rewards: STAKERS amounts plus positions
+    pub total_weight: Uint128,
+    /// FOT accrued to stakers and positions and not claimed yet, up to last_update
+    pub liabilities: Uint128,
+    /// Loyalty and boost weight of stakers on top of their stake, earning alongside total_weight
+    #[serde(default)]
+    pub bonus_weight: Uint128,
+    /// Sum of IOUS, part of liabilities
+    #[serde(default)]
+    pub ious: Uint128,
+    /// Last IOU settled by a funding, the next funding settles the page after it
+    #[serde(default)]
+    pub iou_cursor: Option<Addr>
+}
+
+pub const REWARD_STATE_KEY: &str = "pool_reward_state";
+pub const REWARD_STATE: Map<u64, RewardState> = Map::new(REWARD_STATE_KEY);
+
+pub const REWARD_INDEX_KEY: &str = "pool_reward_index";
+/// RewardState.reward_per_token each (pool_id, staker) reward was last settled at
+pub const REWARD_INDEX: Map<(u64, Addr), Decimal256> = Map::new(REWARD_INDEX_KEY);
+
+/// Start of a staker's uninterrupted stake and the loyalty and boost multiplier the pool's
+/// bonus_weight counts it with, as of its last settlement
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakeAge {
+    pub since: u64,
+    pub multiplier: Decimal
+}
+
+pub const STAKE_AGES_KEY: &str = "pool_stake_ages";
+/// (pool_id, staker) -> stake age, kept beside STAKERS whose stored tuple stays as is
+pub const STAKE_AGES: Map<(u64, Addr), StakeAge> = Map::new(STAKE_AGES_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct BoostLock {
+    pub bfot: Uint128,
+    pub nfts: Vec<LockedNft>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockedNft {
+    pub collection: Addr,
+    pub token_id: String
+}
+
+pub const BOOST_LOCKS_KEY: &str = "pool_boost_locks";
+/// (pool_id, staker) -> bFOT and NFTs locked for boost
+pub const BOOST_LOCKS: Map<(u64, Addr), BoostLock> = Map::new(BOOST_LOCKS_KEY);
+
+pub const IOUS_KEY: &str = "pool_ious";
+/// FOT owed to (pool_id, staker) whose claims were only partially paid, settled when the pool is refunded
+pub const IOUS: Map<(u64, Addr), Uint128> = Map::new(IOUS_KEY);
+
+pub const IMPORT_BATCHES_KEY: &str = "pool_import_batches";
+/// (pool_id, AddStakers batch id) already applied -> block height it was imported at
+pub const IMPORT_BATCHES: Map<(u64, &str), u64> = Map::new(IMPORT_BATCHES_KEY);
+
+/// sFOT distribution claimed with merkle proofs of (address, amount) leaves
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SfotStage {
+    /// Hex encoded sha256 merkle root
+    pub merkle_root: String,
+    pub sfot_token_address: Addr,
+    pub total_amount: Uint128,
+    pub claimed_amount: Uint128,
+    pub start: Option<Scheduled>,
+    pub expiration: Expiration,
+    /// Whether the owner took back the unclaimed amount after expiration
+    pub reclaimed: bool
+}
+
+pub const LATEST_SFOT_STAGE_KEY: &str = "latest_sfot_stage";
+pub const LATEST_SFOT_STAGE: Item<u8> = Item::new(LATEST_SFOT_STAGE_KEY);
+
+pub const SFOT_STAGES_KEY: &str = "sfot_stages";
+pub const SFOT_STAGES: Map<u8, SfotStage> = Map::new(SFOT_STAGES_KEY);
+
+/// sFOT of all stages. Stages are contract-wide, so they are not counted in any pool's stats.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct SfotTotals {
+    pub registered: Uint128,
+    pub claimed: Uint128,
+    /// Taken back by the admin from expired stages
+    pub reclaimed: Uint128
+}
+
+pub const SFOT_TOTALS_KEY: &str = "sfot_totals";
+pub const SFOT_TOTALS: Item<SfotTotals> = Item::new(SFOT_TOTALS_KEY);
 
-pub const UNSTAKING_KEY: &str = "unstaking";
-pub const UNSTAKING: Map<Addr, Vec<(Uint128, u64)>> = Map::new(UNSTAKING_KEY);
+pub const SFOT_CLAIMED_KEY: &str = "sfot_claimed";
+/// (stage, address) -> claimed
+pub const SFOT_CLAIMED: Map<(u8, Addr), bool> = Map::new(SFOT_CLAIMED_KEY);